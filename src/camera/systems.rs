@@ -1,4 +1,4 @@
-use crate::{PhysicsSet, Player};
+use crate::{interpolate_transform, Player};
 use bevy::prelude::*;
 use bevy_light_2d::light::AmbientLight2d;
 
@@ -11,7 +11,7 @@ impl Plugin for CameraControllerPlugin {
         app.add_systems(Startup, spawn_player_camera).add_systems(
             Update,
             camera_follow_player
-                .after(PhysicsSet::Sync)
+                .after(interpolate_transform)
                 .before(TransformSystem::TransformPropagate),
         );
     }
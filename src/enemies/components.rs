@@ -5,41 +5,58 @@ use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 
 use crate::{
-    AnimationList, CharacterControllerBundle, Facing, FallBundle, GraphicsState, HealthBundle,
-    JumpBundle, PlayerGraphicsBundle, RunBundle, StateAnimation,
+    AnimationList, AsepriteSheetPath, CharacterControllerBundle, ContactDamage, DamageType,
+    DeathPolicy, Facing, GraphicsState, HealthBundle, HealthbarStyle, MovementProfileName,
+    PlaybackSpeed, PlayerGraphicsBundle, SheetFrames, StateAnimation, StateTransitions,
+    StatusEffect, StatusEffects,
 };
 
 #[derive(Bundle, LdtkEntity)]
 pub struct HahaBundle {
     name: Name,
     controller: CharacterControllerBundle,
+    #[with(haha_movement_profile)]
+    movement_profile: MovementProfileName,
     health: HealthBundle,
+    contact_damage: ContactDamage,
     player_graphics_bundle: PlayerGraphicsBundle,
-    #[sprite_sheet_bundle("sprites/haha.png", 20, 50, 4, 2, 1, 0, 0)]
+    aseprite_sheet: AsepriteSheetPath,
     sprite_sheet_bundle: LdtkSpriteSheetBundle,
+    status_effects: StatusEffects,
+}
+
+fn haha_movement_profile(entity_instance: &EntityInstance) -> MovementProfileName {
+    MovementProfileName::from_entity_instance(entity_instance, "haha")
 }
 
 impl Default for HahaBundle {
     fn default() -> Self {
         Self {
             name: Name::new("Haha"),
-            controller: CharacterControllerBundle::new(Collider::rectangle(20., 50.))
-                .with_movement(
-                    0.8,
-                    0.8,
-                    RunBundle::default(),
-                    JumpBundle::default(),
-                    FallBundle::default(),
-                    40.,
-                    30,
-                ),
-            health: HealthBundle::new(100., [[0., 0.], [1., 1.]], 300),
+            controller: CharacterControllerBundle::new(Collider::rectangle(20., 50.)),
+            movement_profile: MovementProfileName::new("haha"),
+            health: HealthBundle::new(100.)
+                .with_resistance(DamageType::SWING, 0.5)
+                .with_death_policy(DeathPolicy::Animate(GraphicsState::custom("die")))
+                .with_healthbar_style(HealthbarStyle::default().with_segments(4)),
+            contact_damage: ContactDamage::new(10., 500)
+                .with_effect(StatusEffect::poison(2., 3000)),
             player_graphics_bundle: PlayerGraphicsBundle {
                 facing: Facing::default(),
                 state: GraphicsState::Falling,
+                // Frames come from the tags in haha.aseprite.json, Idle plays its tag as it is
                 animation_list: AnimationList(HashMap::from([
-                    (GraphicsState::Idle, StateAnimation::new_timer(0, 3, 450)),
-                    (GraphicsState::Running, StateAnimation::new_timer(4, 7, 300)),
+                    (
+                        GraphicsState::Running,
+                        StateAnimation::new_sheet_timer()
+                            .with_playback_speed(PlaybackSpeed::HorizontalVelocity(30.)),
+                    ),
+                    // TODO: haha has no death tag yet, wind the idle cycle back until it does
+                    (
+                        GraphicsState::custom("die"),
+                        StateAnimation::new_sheet_timer()
+                            .with_sheet_frames(SheetFrames::ReversedTag("Idle".to_string())),
+                    ),
                     //(GraphicsState::Jumping, StateAnimation::new_velocity_list(6, 11, vec![
                     //                                                           100.,
                     //                                                           200.,
@@ -55,8 +72,11 @@ impl Default for HahaBundle {
                     //                                                           -250.,
                     //])),
                 ])),
+                transitions: StateTransitions::default(),
             },
+            aseprite_sheet: AsepriteSheetPath::new("sprites/haha.aseprite.json"),
             sprite_sheet_bundle: LdtkSpriteSheetBundle::default(),
+            status_effects: StatusEffects::default(),
         }
     }
 }
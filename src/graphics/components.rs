@@ -1,8 +1,14 @@
 use std::{collections::HashMap, time::Duration};
 
-use bevy::{prelude::*, sprite::Anchor};
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, ParseAssetPathError},
+    prelude::*,
+    sprite::Anchor,
+};
 use bevy_ecs_ldtk::LdtkIntCell;
 use bevy_light_2d::light::PointLight2d;
+use serde::Deserialize;
+use thiserror::Error;
 
 /// The direction the entity is facing
 #[derive(Component, Clone, Debug, Default)]
@@ -12,45 +18,162 @@ pub enum Facing {
     Right,
 }
 
+/// Asks for an entity's [`GraphicsState`] to change, which happens once its
+/// [`StateTransitions`] allow it
+#[derive(Event)]
+pub struct StateRequest {
+    pub state: GraphicsState,
+    pub entity: Entity,
+}
+
+/// An event sent when an entity's [`GraphicsState`] has changed
 #[derive(Event)]
 pub struct StateChange {
     pub state: GraphicsState,
     pub entity: Entity,
 }
 
+/// An event sent when an animation played with [`PlaybackMode::Once`] reaches its end
+#[derive(Event)]
+pub struct AnimationFinished {
+    pub entity: Entity,
+    pub state: GraphicsState,
+}
+
+/// What an animation does once it reaches its last frame
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum PlaybackMode {
+    /// Stop on the last frame
+    Once,
+    /// Start over from the first frame
+    #[default]
+    Loop,
+    /// Play backwards to the first frame, then forwards again
+    PingPong,
+}
+
+/// Steps through animation frames that each have their own duration
+#[derive(Debug)]
+pub struct FrameTimer {
+    pub durations: Vec<Duration>,
+    pub mode: PlaybackMode,
+    /// How many frames the current frame is past the first one
+    pub frame: usize,
+    pub elapsed: Duration,
+    /// Whether a ping-pong animation is on its way back
+    pub reversed: bool,
+    pub finished: bool,
+}
+
+impl FrameTimer {
+    pub fn new(durations: Vec<Duration>, mode: PlaybackMode) -> Self {
+        Self {
+            durations,
+            mode,
+            frame: 0,
+            elapsed: Duration::ZERO,
+            reversed: false,
+            finished: false,
+        }
+    }
+    pub fn reset(&mut self) {
+        self.frame = 0;
+        self.elapsed = Duration::ZERO;
+        self.reversed = false;
+        self.finished = false;
+    }
+    /// Returns true on the tick a [`PlaybackMode::Once`] animation finishes its last frame
+    pub fn tick(&mut self, delta: Duration) -> bool {
+        if self.finished || self.durations.iter().all(Duration::is_zero) {
+            return false;
+        }
+        self.elapsed += delta;
+        while let Some(duration) = self.durations.get(self.frame).copied() {
+            if self.elapsed < duration {
+                break;
+            }
+            self.elapsed -= duration;
+            if !self.advance() {
+                self.elapsed = Duration::ZERO;
+                self.finished = true;
+                return true;
+            }
+        }
+        false
+    }
+    /// Moves on to the next frame, false if there isn't one
+    fn advance(&mut self) -> bool {
+        let last = self.durations.len().saturating_sub(1);
+        match self.mode {
+            PlaybackMode::Once => {
+                if self.frame >= last {
+                    return false;
+                }
+                self.frame += 1;
+            }
+            PlaybackMode::Loop => {
+                self.frame = if self.frame >= last {
+                    0
+                } else {
+                    self.frame + 1
+                };
+            }
+            PlaybackMode::PingPong => {
+                if last == 0 {
+                    return true;
+                }
+                if self.frame >= last {
+                    self.reversed = true;
+                } else if self.frame == 0 {
+                    self.reversed = false;
+                }
+                if self.reversed {
+                    self.frame -= 1;
+                } else {
+                    self.frame += 1;
+                }
+            }
+        }
+        true
+    }
+}
+
 #[derive(Debug)]
 pub enum AnimTimer {
-    AnimTimer(Timer),
+    AnimTimer(FrameTimer),
     AnimVelocityList(Vec<f32>),
 }
 
 impl AnimTimer {
-    pub fn timer_fraction(&self) -> f32 {
+    /// Advances a timer animation, velocity lists don't depend on time.
+    /// Returns true on the tick a [`PlaybackMode::Once`] animation finishes
+    pub fn tick(&mut self, delta: Duration) -> bool {
         match self {
-            AnimTimer::AnimTimer(timer) => timer.fraction(),
-            AnimTimer::AnimVelocityList(_velocity_list) => {
-                panic!("Doesn't have a timer");
-            }
+            AnimTimer::AnimTimer(timer) => timer.tick(delta),
+            AnimTimer::AnimVelocityList(_) => false,
         }
     }
-    pub fn tick_timer(&mut self, delta: Duration) {
-        match self {
-            AnimTimer::AnimTimer(timer) => {
-                timer.tick(delta);
-            }
-            AnimTimer::AnimVelocityList(_velocity_list) => {
-                panic!("Doesn't have a timer");
-            }
+    pub fn reset(&mut self) {
+        if let AnimTimer::AnimTimer(timer) = self {
+            timer.reset();
         }
     }
-    pub fn velocity_fraction(&self, velocity: f32) -> f32 {
+    /// How far through its frames the animation is, from 0 to 1, for the given velocity
+    pub fn progress(&self, velocity: f32) -> f32 {
         match self {
-            AnimTimer::AnimTimer(_timer) => {
-                panic!("Doesn't have velocity lists");
+            AnimTimer::AnimTimer(timer) => {
+                let last = timer.durations.len().saturating_sub(1);
+                if last == 0 {
+                    0.
+                } else {
+                    timer.frame.min(last) as f32 / last as f32
+                }
             }
             AnimTimer::AnimVelocityList(velocity_thresholds) => {
+                if velocity_thresholds.is_empty() {
+                    return 0.;
+                }
                 let mut threshold_index: f32 = 0.;
-                //velocity_thresholds.clone().iter().filter(|threshold| threshold < )
                 for (i, velocity_threshold) in velocity_thresholds.iter().enumerate() {
                     if velocity.abs() < velocity_threshold.abs() {
                         break;
@@ -63,37 +186,105 @@ impl AnimTimer {
     }
 }
 
+/// How fast a timer animation plays back
+#[derive(Debug, Default, Clone, Copy)]
+pub enum PlaybackSpeed {
+    /// Always takes the animation's duration
+    #[default]
+    Fixed,
+    /// Plays at its normal rate when moving sideways at this speed, faster or slower otherwise
+    HorizontalVelocity(f32),
+    /// Plays at its normal rate when moving up or down at this speed, faster or slower otherwise
+    VerticalVelocity(f32),
+}
+
+impl PlaybackSpeed {
+    /// The rate to tick the animation at for the given (gravity relative) velocity
+    pub fn rate(&self, velocity: Vec2) -> f32 {
+        match self {
+            PlaybackSpeed::Fixed => 1.,
+            PlaybackSpeed::HorizontalVelocity(speed) => velocity.x.abs() / speed.max(f32::EPSILON),
+            PlaybackSpeed::VerticalVelocity(speed) => velocity.y.abs() / speed.max(f32::EPSILON),
+        }
+    }
+}
+
 #[derive(Component, Default)]
 pub struct AnimationList(pub HashMap<GraphicsState, StateAnimation>);
 
+/// Where an animation takes its frames from once its entity's [`AsepriteSheet`] loads
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum SheetFrames {
+    /// The tag named after the animation's state, if there is one
+    #[default]
+    State,
+    /// The tag with this name
+    Tag(String),
+    /// The tag with this name, played backwards
+    ReversedTag(String),
+}
+
 //#[derive(Component)]
 #[derive(Debug)]
 pub struct StateAnimation {
     pub indexes: (usize, usize),
     pub anim_timer: AnimTimer,
+    pub playback_speed: PlaybackSpeed,
+    pub sheet_frames: SheetFrames,
     //pub trigger_state: GraphicsState
 }
 
 impl StateAnimation {
-    pub fn new_timer(
-        start: usize,
-        finish: usize,
-        /* trigger_state: GraphicsState,*/ duration: u64,
-    ) -> Self {
+    /// An animation where every frame from `start` to `finish` has its own duration
+    pub fn new_frames(start: usize, finish: usize, durations: Vec<u64>) -> Self {
         Self {
             indexes: (start, finish),
-            anim_timer: AnimTimer::AnimTimer(Timer::new(
-                Duration::from_millis(duration),
-                TimerMode::Repeating,
+            anim_timer: AnimTimer::AnimTimer(FrameTimer::new(
+                durations.into_iter().map(Duration::from_millis).collect(),
+                PlaybackMode::Loop,
             )),
-            //trigger_state
+            playback_speed: PlaybackSpeed::Fixed,
+            sheet_frames: SheetFrames::State,
         }
     }
     pub fn new_velocity_list(start: usize, finish: usize, velocity_list: Vec<f32>) -> Self {
         Self {
             indexes: (start, finish),
             anim_timer: AnimTimer::AnimVelocityList(velocity_list),
+            playback_speed: PlaybackSpeed::Fixed,
+            sheet_frames: SheetFrames::State,
+        }
+    }
+    /// A timer animation that gets its frames and durations from the entity's [`AsepriteSheet`],
+    /// it shows the first frame of the atlas until the sheet loads
+    pub fn new_sheet_timer() -> Self {
+        Self::new_frames(0, 0, vec![0])
+    }
+    /// A velocity list animation that gets its frames from the entity's [`AsepriteSheet`]
+    pub fn new_sheet_velocity_list(velocity_list: Vec<f32>) -> Self {
+        Self::new_velocity_list(0, 0, velocity_list)
+    }
+    pub fn with_playback_speed(mut self, playback_speed: PlaybackSpeed) -> Self {
+        self.playback_speed = playback_speed;
+        self
+    }
+    pub fn with_mode(mut self, mode: PlaybackMode) -> Self {
+        if let AnimTimer::AnimTimer(timer) = &mut self.anim_timer {
+            timer.mode = mode;
         }
+        self
+    }
+    pub fn with_sheet_frames(mut self, sheet_frames: SheetFrames) -> Self {
+        self.sheet_frames = sheet_frames;
+        self
+    }
+    /// Plays the same frames backwards
+    pub fn reversed(mut self) -> Self {
+        self.indexes = (self.indexes.1, self.indexes.0);
+        if let AnimTimer::AnimTimer(timer) = &mut self.anim_timer {
+            timer.durations.reverse();
+        }
+        self
     }
     //pub fn new_timer(start: usize, finish: usize, trigger_state: GraphicsState, duration: u64) -> Self {
     //    Self {
@@ -105,43 +296,84 @@ impl StateAnimation {
     pub fn frame_range(&self) -> usize {
         self.indexes.0.abs_diff(self.indexes.1)
     }
-    pub fn frame_from_percent(&self) -> usize {
-        if !self.has_velocity_list() {
-            self.indexes.0
-                + self.frame_range() * ((self.anim_timer.timer_fraction() * 100.) as usize) / 100
-        } else {
-            panic!("Doesn't have a timer");
-        }
-    }
     pub fn has_velocity_list(&self) -> bool {
         match self.anim_timer {
             AnimTimer::AnimTimer(_) => false,
             AnimTimer::AnimVelocityList(_) => true,
         }
     }
-    pub fn frame_from_velocity(&self, velocity: f32) -> usize {
-        if self.has_velocity_list() {
-            let frame: usize;
-            if self.indexes.0 < self.indexes.1 {
-                frame = self.indexes.0
-                    + self.frame_range()
-                        * ((self.anim_timer.velocity_fraction(velocity) * 100.) as usize)
-                        / 100;
-            } else {
-                frame = self.indexes.0
-                    - self.frame_range()
-                        * ((self.anim_timer.velocity_fraction(velocity) * 100.) as usize)
-                        / 100;
+    /// Advances the animation by `delta` at its [`PlaybackSpeed`] for the given (gravity relative) velocity.
+    /// Returns true on the tick a [`PlaybackMode::Once`] animation finishes
+    pub fn tick(&mut self, delta: Duration, velocity: Vec2) -> bool {
+        let rate = self.playback_speed.rate(velocity);
+        self.anim_timer.tick(delta.mul_f32(rate))
+    }
+    /// The atlas index to show, velocity lists pick theirs from the vertical velocity
+    pub fn frame(&self, velocity: Vec2) -> usize {
+        let frame = match &self.anim_timer {
+            AnimTimer::AnimTimer(timer) => timer.frame,
+            AnimTimer::AnimVelocityList(_) => {
+                (self.frame_range() as f32 * self.anim_timer.progress(velocity.y)) as usize
             }
-            //info!("Velocity:\t{}", velocity);
-            //info!("Fram:\t\t{}", frame);
-            frame
+        }
+        .min(self.frame_range());
+        if self.indexes.0 <= self.indexes.1 {
+            self.indexes.0 + frame
         } else {
-            panic!("Doesn't have a velocity list");
+            self.indexes.0 - frame
+        }
+    }
+    /// Checks the animation can play on an atlas with `atlas_len` frames, if it's known
+    pub fn validate(&self, atlas_len: Option<usize>) -> Result<(), AnimationError> {
+        match &self.anim_timer {
+            AnimTimer::AnimTimer(timer) => {
+                if timer.durations.len() != self.frame_range() + 1 {
+                    return Err(AnimationError::DurationCount {
+                        frames: self.frame_range() + 1,
+                        durations: timer.durations.len(),
+                    });
+                }
+            }
+            AnimTimer::AnimVelocityList(velocity_thresholds) => {
+                if velocity_thresholds.is_empty() {
+                    return Err(AnimationError::EmptyVelocityList);
+                }
+            }
         }
+        if let Some(atlas_len) = atlas_len {
+            let last = self.indexes.0.max(self.indexes.1);
+            if last >= atlas_len {
+                return Err(AnimationError::OutsideAtlas {
+                    index: last,
+                    atlas_len,
+                });
+            }
+        }
+        Ok(())
     }
 }
 
+/// Why a [`StateAnimation`] can't play as configured
+#[derive(Debug, Clone, Error)]
+pub enum AnimationError {
+    #[error("frame {index} is outside the atlas of {atlas_len} frames")]
+    OutsideAtlas { index: usize, atlas_len: usize },
+    #[error("the velocity list is empty")]
+    EmptyVelocityList,
+    #[error("{durations} frame durations were given for {frames} frames")]
+    DurationCount { frames: usize, durations: usize },
+    #[error("the sheet has no {0:?} tag")]
+    MissingTag(String),
+}
+
+/// An event sent when an entity's [`AnimationList`] has an animation that can't play as configured
+#[derive(Event, Debug)]
+pub struct AnimationMisconfigured {
+    pub entity: Entity,
+    pub state: GraphicsState,
+    pub error: AnimationError,
+}
+
 //#[derive(Component)]
 //pub struct StateAnimationSpeed {
 //    pub idle_timer: Timer,
@@ -168,9 +400,35 @@ pub enum GraphicsState {
     Running,
     Jumping,
     Falling,
+    Climbing,
+    Swimming,
+    Crouching,
+    Sliding,
+    Landing,
+    /// A state only some entities have, like attacking or getting hurt, played through a [`GraphicsStateOverride`]
+    Custom(String),
 }
 
 impl GraphicsState {
+    pub fn custom(name: &str) -> Self {
+        GraphicsState::Custom(name.to_lowercase())
+    }
+    /// The state an Aseprite tag of the same name (ignoring case, with or without the "ing")
+    /// animates, tags that aren't named after a built in state are custom states
+    pub fn from_tag(tag: &str) -> Self {
+        match tag.to_lowercase().as_str() {
+            "idle" => GraphicsState::Idle,
+            "run" | "running" => GraphicsState::Running,
+            "jump" | "jumping" => GraphicsState::Jumping,
+            "fall" | "falling" => GraphicsState::Falling,
+            "climb" | "climbing" => GraphicsState::Climbing,
+            "swim" | "swimming" => GraphicsState::Swimming,
+            "crouch" | "crouching" => GraphicsState::Crouching,
+            "slide" | "sliding" => GraphicsState::Sliding,
+            "land" | "landing" => GraphicsState::Landing,
+            _ => GraphicsState::custom(tag),
+        }
+    }
     pub fn is_state(&self, graphics_state: &GraphicsState) -> bool {
         self == graphics_state
     }
@@ -179,12 +437,75 @@ impl GraphicsState {
     }
 }
 
+/// Plays a state instead of the one the movement layer picks, until it's removed.
+/// It's removed on its own once a [`PlaybackMode::Once`] animation of the state finishes
+#[derive(Component, Clone, Debug)]
+#[component(storage = "SparseSet")]
+pub struct GraphicsStateOverride(pub GraphicsState);
+
+/// Rules for how an entity moves between [`GraphicsState`]s, without any it changes state straight away
+#[derive(Component, Default, Debug)]
+pub struct StateTransitions {
+    /// How long a state is kept before a lower or equal priority state can replace it
+    pub min_dwell: HashMap<GraphicsState, Duration>,
+    /// States missing from here have a priority of 0
+    pub priorities: HashMap<GraphicsState, i32>,
+    /// The state passed through when going from the first state to the second
+    pub via: HashMap<(GraphicsState, GraphicsState), GraphicsState>,
+    /// How long the entity has been in its current state
+    pub elapsed: Duration,
+}
+
+impl StateTransitions {
+    pub fn with_min_dwell(mut self, state: GraphicsState, duration: u64) -> Self {
+        self.min_dwell
+            .insert(state, Duration::from_millis(duration));
+        self
+    }
+    pub fn with_priority(mut self, state: GraphicsState, priority: i32) -> Self {
+        self.priorities.insert(state, priority);
+        self
+    }
+    /// Goes through `via` on the way from `from` to `to`
+    pub fn with_via(mut self, from: GraphicsState, to: GraphicsState, via: GraphicsState) -> Self {
+        self.via.insert((from, to), via);
+        self
+    }
+    pub fn priority(&self, state: &GraphicsState) -> i32 {
+        self.priorities.get(state).copied().unwrap_or(0)
+    }
+    /// The state to change to from `current` when `requested` is asked for, if any
+    pub fn next_state(
+        &self,
+        current: &GraphicsState,
+        requested: &GraphicsState,
+    ) -> Option<GraphicsState> {
+        if current == requested {
+            return None;
+        }
+        let dwelling = self
+            .min_dwell
+            .get(current)
+            .is_some_and(|min_dwell| self.elapsed < *min_dwell);
+        if dwelling && self.priority(requested) <= self.priority(current) {
+            return None;
+        }
+        Some(
+            self.via
+                .get(&(current.clone(), requested.clone()))
+                .unwrap_or(requested)
+                .clone(),
+        )
+    }
+}
+
 #[derive(Bundle, Default)]
 pub struct PlayerGraphicsBundle {
     //pub graphics_bundle: GraphicsBundle,
     pub facing: Facing,
     pub state: GraphicsState,
     pub animation_list: AnimationList,
+    pub transitions: StateTransitions,
 }
 
 #[derive(Bundle, Default)]
@@ -256,3 +577,240 @@ impl Default for GreenLightingBundle {
         }
     }
 }
+
+/// The direction an Aseprite tag plays in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AsepriteDirection {
+    Forward,
+    Reverse,
+    PingPong,
+    PingPongReverse,
+}
+
+/// A named range of frames in an Aseprite sheet
+#[derive(Debug, Clone)]
+pub struct AsepriteTag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    pub direction: AsepriteDirection,
+    /// How many times the tag plays, forever if `None`
+    pub repeat: Option<u32>,
+}
+
+/// A sprite sheet exported from Aseprite as JSON (array or hash), along with its image
+/// and the atlas layout of its frames
+#[derive(Asset, TypePath, Debug)]
+pub struct AsepriteSheet {
+    pub image: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    /// The duration of each frame in milliseconds
+    pub frame_durations: Vec<u64>,
+    pub tags: Vec<AsepriteTag>,
+}
+
+impl AsepriteSheet {
+    /// Builds the animation for a tag, with its frame durations and playback
+    pub fn tag_animation(&self, tag: &AsepriteTag) -> StateAnimation {
+        let mut durations = self
+            .frame_durations
+            .get(tag.from..=tag.to)
+            .map_or(Vec::new(), |durations| durations.to_vec());
+        let (start, finish) = match tag.direction {
+            AsepriteDirection::Reverse | AsepriteDirection::PingPongReverse => {
+                durations.reverse();
+                (tag.to, tag.from)
+            }
+            AsepriteDirection::Forward | AsepriteDirection::PingPong => (tag.from, tag.to),
+        };
+        let mode = match (tag.direction, tag.repeat) {
+            (AsepriteDirection::PingPong | AsepriteDirection::PingPongReverse, _) => {
+                PlaybackMode::PingPong
+            }
+            (_, Some(1)) => PlaybackMode::Once,
+            _ => PlaybackMode::Loop,
+        };
+        StateAnimation::new_frames(start, finish, durations).with_mode(mode)
+    }
+    /// The tag with this name, ignoring case
+    pub fn tag(&self, name: &str) -> Option<&AsepriteTag> {
+        self.tags
+            .iter()
+            .find(|tag| tag.name.eq_ignore_ascii_case(name))
+    }
+    /// The animation `sheet_frames` picks out for `state`, `None` if the sheet doesn't have the tag
+    pub fn sheet_animation(
+        &self,
+        state: &GraphicsState,
+        sheet_frames: &SheetFrames,
+    ) -> Option<StateAnimation> {
+        let (tag, reversed) = match sheet_frames {
+            SheetFrames::State => (
+                self.tags
+                    .iter()
+                    .find(|tag| GraphicsState::from_tag(&tag.name) == *state)?,
+                false,
+            ),
+            SheetFrames::Tag(name) => (self.tag(name)?, false),
+            SheetFrames::ReversedTag(name) => (self.tag(name)?, true),
+        };
+        let animation = self
+            .tag_animation(tag)
+            .with_sheet_frames(sheet_frames.clone());
+        Some(if reversed {
+            animation.reversed()
+        } else {
+            animation
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct AsepriteRect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct AsepriteSize {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct AsepriteFrame {
+    frame: AsepriteRect,
+    duration: u64,
+}
+
+/// Aseprite exports frames either as a list or as a map keyed by file name
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AsepriteFrames {
+    Array(Vec<AsepriteFrame>),
+    Hash(serde_json::Map<String, serde_json::Value>),
+}
+
+#[derive(Deserialize)]
+struct AsepriteFrameTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+    /// Left out, or "0", when the tag repeats forever
+    #[serde(default)]
+    repeat: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AsepriteMeta {
+    image: String,
+    size: AsepriteSize,
+    #[serde(rename = "frameTags", default)]
+    frame_tags: Vec<AsepriteFrameTag>,
+}
+
+#[derive(Deserialize)]
+struct AsepriteJson {
+    frames: AsepriteFrames,
+    meta: AsepriteMeta,
+}
+
+#[derive(Default)]
+pub struct AsepriteSheetLoader;
+
+#[derive(Debug, Error)]
+pub enum AsepriteSheetLoaderError {
+    #[error("Could not read Aseprite sheet: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse Aseprite sheet: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Could not find the Aseprite sheet's image: {0}")]
+    ImagePath(#[from] ParseAssetPathError),
+}
+
+impl AssetLoader for AsepriteSheetLoader {
+    type Asset = AsepriteSheet;
+    type Settings = ();
+    type Error = AsepriteSheetLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let json: AsepriteJson = serde_json::from_slice(&bytes)?;
+
+        let frames = match json.frames {
+            AsepriteFrames::Array(frames) => frames,
+            // `preserve_order` keeps the hash in frame order
+            AsepriteFrames::Hash(frames) => frames
+                .into_iter()
+                .map(|(_, frame)| serde_json::from_value(frame))
+                .collect::<Result<Vec<AsepriteFrame>, _>>()?,
+        };
+        let mut layout =
+            TextureAtlasLayout::new_empty(UVec2::new(json.meta.size.w, json.meta.size.h));
+        for AsepriteFrame { frame, .. } in frames.iter() {
+            layout.add_texture(URect::new(
+                frame.x,
+                frame.y,
+                frame.x + frame.w,
+                frame.y + frame.h,
+            ));
+        }
+        let tags = json
+            .meta
+            .frame_tags
+            .into_iter()
+            .map(|tag| AsepriteTag {
+                direction: match tag.direction.as_str() {
+                    "reverse" => AsepriteDirection::Reverse,
+                    "pingpong" => AsepriteDirection::PingPong,
+                    "pingpong_reverse" => AsepriteDirection::PingPongReverse,
+                    _ => AsepriteDirection::Forward,
+                },
+                repeat: tag
+                    .repeat
+                    .and_then(|repeat| repeat.parse().ok())
+                    .filter(|repeat| *repeat > 0),
+                name: tag.name,
+                from: tag.from,
+                to: tag.to,
+            })
+            .collect();
+
+        // The image is next to the JSON
+        let image_path = load_context.asset_path().resolve_embed(&json.meta.image)?;
+        Ok(AsepriteSheet {
+            image: load_context.load(image_path),
+            layout: load_context.add_labeled_asset("layout".to_string(), layout),
+            frame_durations: frames.iter().map(|frame| frame.duration).collect(),
+            tags,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["aseprite.json"]
+    }
+}
+
+/// The Aseprite sheet an entity's [`AnimationList`] and atlas come from
+#[derive(Component, Clone, Debug)]
+pub struct AsepriteSheetPath(pub String);
+
+impl AsepriteSheetPath {
+    pub fn new(path: &str) -> Self {
+        Self(path.to_string())
+    }
+}
+
+/// The loaded [`AsepriteSheet`] of an entity
+#[derive(Component)]
+pub struct AsepriteSheetHandle(pub Handle<AsepriteSheet>);
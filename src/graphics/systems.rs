@@ -1,10 +1,18 @@
+use std::time::Duration;
+
 use super::prelude::*;
 use avian2d::prelude::LinearVelocity;
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
 use bevy_ecs_ldtk::app::LdtkIntCellAppExt;
 use bevy_light_2d::plugin::Light2dPlugin;
 
-use crate::{AnimationList, Facing, Grounded, StateChange};
+use crate::{
+    AnimationList, Crouching, Facing, Grounded, JumpFallState, MoveState, StateChange,
+    StateRequest, Submerged, UpVector,
+};
 
 use super::prelude::GraphicsState;
 
@@ -12,10 +20,32 @@ pub struct GraphicsPlugin;
 
 impl Plugin for GraphicsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<StateChange>()
+        app.add_event::<StateRequest>()
+            .add_event::<StateChange>()
+            .add_event::<AnimationFinished>()
+            .add_event::<AnimationMisconfigured>()
+            .init_asset::<AsepriteSheet>()
+            .init_asset_loader::<AsepriteSheetLoader>()
+            .add_systems(
+                PreUpdate,
+                (
+                    load_aseprite_sheets,
+                    apply_aseprite_sheets,
+                    validate_animation_lists,
+                )
+                    .chain(),
+            )
             .add_systems(
                 Update,
-                (flip_sprite, state_machine, set_state, set_sprite_from_state).chain(),
+                (
+                    flip_sprite,
+                    state_machine,
+                    set_state,
+                    restart_state_animations,
+                    set_sprite_from_state,
+                    clear_finished_overrides,
+                )
+                    .chain(),
             )
             .register_ldtk_int_cell_for_layer::<GreenLightingBundle>("IntGridLighting", 1)
             .insert_resource(Msaa::Off)
@@ -39,87 +69,202 @@ fn flip_sprite(mut query: Query<(&mut Transform, &mut Sprite, &Facing, &Name)>)
     }
 }
 
+/// Changes to the latest requested state once the entity's [`StateTransitions`] allow it,
+/// sending a [`StateChange`] when it does.
 fn set_state(
-    mut query: Query<&mut GraphicsState>,
+    mut query: Query<(Entity, &mut GraphicsState, Option<&mut StateTransitions>)>,
+    mut state_request_event_reader: EventReader<StateRequest>,
+    mut state_change_event_writer: EventWriter<StateChange>,
+    time: Res<Time>,
+) {
+    let mut requested_states: HashMap<Entity, GraphicsState> = HashMap::new();
+    for state_request in state_request_event_reader.read() {
+        requested_states.insert(state_request.entity, state_request.state.clone());
+    }
+
+    for (entity, mut graphics_state, transitions) in query.iter_mut() {
+        let requested_state = requested_states.remove(&entity);
+        let new_state = match transitions {
+            Some(mut transitions) => {
+                transitions.elapsed += time.delta();
+                let new_state = requested_state.and_then(|requested_state| {
+                    transitions.next_state(&graphics_state, &requested_state)
+                });
+                if new_state.is_some() {
+                    transitions.elapsed = Duration::ZERO;
+                }
+                new_state
+            }
+            None => requested_state,
+        };
+        let Some(new_state) = new_state.filter(|new_state| *new_state != *graphics_state) else {
+            continue;
+        };
+        *graphics_state = new_state.clone();
+        state_change_event_writer.send(StateChange {
+            state: new_state,
+            entity,
+        });
+    }
+}
+
+/// Starts the animation of a newly changed state from its first frame.
+fn restart_state_animations(
+    mut query: Query<&mut AnimationList>,
     mut state_change_event_reader: EventReader<StateChange>,
 ) {
-    for graphics_state_change in state_change_event_reader.read() {
-        let graphics_state_result = query.get_mut(graphics_state_change.entity);
-        if graphics_state_result.is_ok() {
-            let new_state = graphics_state_change.state.clone();
-            *graphics_state_result.unwrap() = new_state;
+    for state_change in state_change_event_reader.read() {
+        if let Some(state_animation) = query
+            .get_mut(state_change.entity)
+            .ok()
+            .and_then(|animation_list| animation_list.into_inner().0.get_mut(&state_change.state))
+        {
+            state_animation.anim_timer.reset();
         }
     }
 }
+
+type StateMachineEntity<'a> = (
+    Entity,
+    &'a GraphicsState,
+    &'a LinearVelocity,
+    Option<&'a UpVector>,
+    Option<&'a JumpFallState>,
+    Option<&'a MoveState>,
+    Has<Grounded>,
+    Has<Submerged>,
+    Has<Crouching>,
+    Option<&'a GraphicsStateOverride>,
+);
+
+/// Picks the [`GraphicsState`] from the movement layer's own states,
+/// falling back to the velocity for entities without a [`JumpFallState`].
 fn state_machine(
-    query: Query<(&LinearVelocity, Entity, &GraphicsState, Has<Grounded>)>,
-    mut state_change_event_writer: EventWriter<StateChange>,
+    query: Query<StateMachineEntity>,
+    mut state_request_event_writer: EventWriter<StateRequest>,
 ) {
-    for (linear_velocity, entity, state, grounded) in query.iter() {
-        //info!("Linear Velocity (y): {}", linear_velocity.y);
-        if linear_velocity.y > 1. {
-            if state.is_not_state(&GraphicsState::Jumping) {
-                state_change_event_writer.send(StateChange {
-                    state: GraphicsState::Jumping,
-                    entity,
-                });
-            }
-        } else if linear_velocity.y < -0.5 {
-            if state.is_not_state(&GraphicsState::Falling) {
-                state_change_event_writer.send(StateChange {
-                    state: GraphicsState::Falling,
-                    entity,
-                });
-            }
-        } else if linear_velocity.x.abs() > 1. {
-            if state.is_not_state(&GraphicsState::Running) {
-                state_change_event_writer.send(StateChange {
-                    state: GraphicsState::Running,
-                    entity,
-                });
-            }
-        } else if !grounded {
-            if state.is_not_state(&GraphicsState::Idle) {
-                state_change_event_writer.send(StateChange {
-                    state: GraphicsState::Falling,
-                    entity,
-                });
+    for (
+        entity,
+        state,
+        linear_velocity,
+        up_vector,
+        jump_fall_state,
+        move_state,
+        grounded,
+        submerged,
+        crouching,
+        state_override,
+    ) in query.iter()
+    {
+        let new_state = if let Some(state_override) = state_override {
+            state_override.0.clone()
+        } else if matches!(move_state, Some(MoveState::Climbing(_))) {
+            GraphicsState::Climbing
+        } else if submerged {
+            GraphicsState::Swimming
+        } else if matches!(move_state, Some(MoveState::Sliding { .. })) {
+            GraphicsState::Sliding
+        } else if crouching && grounded {
+            GraphicsState::Crouching
+        } else if let Some(jump_fall_state) = jump_fall_state {
+            match jump_fall_state {
+                JumpFallState::Jumping | JumpFallState::WallJumping(_) => GraphicsState::Jumping,
+                JumpFallState::Falling => GraphicsState::Falling,
+                JumpFallState::Idle => match move_state {
+                    Some(MoveState::Running(_) | MoveState::Dashing { .. }) => {
+                        GraphicsState::Running
+                    }
+                    _ => GraphicsState::Idle,
+                },
             }
         } else {
-            if state.is_not_state(&GraphicsState::Idle) {
-                state_change_event_writer.send(StateChange {
-                    state: GraphicsState::Idle,
-                    entity,
-                });
+            // Jumping and falling are relative to the entity's gravity
+            let linear_velocity = up_vector.map_or(linear_velocity.0, |up_vector| {
+                up_vector.to_local(linear_velocity.0)
+            });
+            if linear_velocity.y > 1. {
+                GraphicsState::Jumping
+            } else if linear_velocity.y < -0.5 || !grounded {
+                GraphicsState::Falling
+            } else if linear_velocity.x.abs() > 1. {
+                GraphicsState::Running
+            } else {
+                GraphicsState::Idle
+            }
+        };
+        if state.is_not_state(&new_state) {
+            state_request_event_writer.send(StateRequest {
+                state: new_state,
+                entity,
+            });
+        }
+    }
+}
+
+/// Removes [`GraphicsStateOverride`]s once their animation has played through.
+fn clear_finished_overrides(
+    mut commands: Commands,
+    query: Query<&GraphicsStateOverride>,
+    mut animation_finished_event_reader: EventReader<AnimationFinished>,
+) {
+    for animation_finished in animation_finished_event_reader.read() {
+        if let Ok(state_override) = query.get(animation_finished.entity) {
+            if state_override.0 == animation_finished.state {
+                commands
+                    .entity(animation_finished.entity)
+                    .remove::<GraphicsStateOverride>();
             }
         }
-        //info!("Linear Velocity: {:?}", linear_velocity);
-        //info!("Sprite Index: {}", texture_atlas.index);
     }
 }
 
+type AnimatedSprite<'a> = (
+    Entity,
+    &'a Name,
+    &'a GraphicsState,
+    &'a LinearVelocity,
+    &'a mut AnimationList,
+    &'a mut TextureAtlas,
+    Option<&'a UpVector>,
+);
+
 fn set_sprite_from_state(
-    mut query: Query<(
-        &Name,
-        &GraphicsState,
-        &LinearVelocity,
-        &mut AnimationList,
-        &mut TextureAtlas,
-    )>,
+    mut query: Query<AnimatedSprite>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    mut animation_finished_event_writer: EventWriter<AnimationFinished>,
     time: Res<Time>,
 ) {
-    for (name, graphics_state, linear_velocity, mut animation_list, mut texture_atlas) in
-        query.iter_mut()
+    for (
+        entity,
+        name,
+        graphics_state,
+        linear_velocity,
+        mut animation_list,
+        mut texture_atlas,
+        up_vector,
+    ) in query.iter_mut()
     {
+        let linear_velocity = up_vector.map_or(linear_velocity.0, |up_vector| {
+            up_vector.to_local(linear_velocity.0)
+        });
         match animation_list.0.get_mut(graphics_state) {
             Some(state_animation) => {
                 //info!("Here in {:?} graphics state", name);
-                if state_animation.has_velocity_list() {
-                    //info!("Velocity: {}", linear_velocity.y);
-                    texture_atlas.index = state_animation.frame_from_velocity(linear_velocity.y);
-                } else {
-                    state_animation.anim_timer.tick_timer(time.delta());
-                    texture_atlas.index = state_animation.frame_from_percent();
+                let just_finished = state_animation.tick(time.delta(), linear_velocity);
+                let frame = state_animation.frame(linear_velocity);
+                // Misconfigured animations are reported when they're set up, don't point the
+                // sprite outside its atlas in the meantime
+                let atlas_len = layouts
+                    .get(&texture_atlas.layout)
+                    .map(|layout| layout.textures.len());
+                if !atlas_len.is_some_and(|atlas_len| frame >= atlas_len) {
+                    texture_atlas.index = frame;
+                }
+                if just_finished {
+                    animation_finished_event_writer.send(AnimationFinished {
+                        entity,
+                        state: graphics_state.clone(),
+                    });
                 }
             }
             None => {
@@ -147,3 +292,140 @@ fn set_sprite_from_state(
         //}
     }
 }
+
+/// Starts loading the [`AsepriteSheet`] at a new [`AsepriteSheetPath`].
+fn load_aseprite_sheets(
+    mut commands: Commands,
+    query: Query<(Entity, &AsepriteSheetPath), Changed<AsepriteSheetPath>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (entity, path) in query.iter() {
+        commands
+            .entity(entity)
+            .insert(AsepriteSheetHandle(asset_server.load(&path.0)));
+    }
+}
+
+type AsepriteSprite<'a> = (
+    Entity,
+    Ref<'a, AsepriteSheetHandle>,
+    &'a mut Handle<Image>,
+    &'a mut TextureAtlas,
+    &'a mut AnimationList,
+);
+
+/// Points sprites at their [`AsepriteSheet`]'s image and atlas, and fills their
+/// [`AnimationList`] from the tags picked by each animation's [`SheetFrames`] or named after
+/// a [`GraphicsState`]. Velocity list animations keep their velocities and only take the tag's frames.
+fn apply_aseprite_sheets(
+    mut asset_events: EventReader<AssetEvent<AsepriteSheet>>,
+    sheets: Res<Assets<AsepriteSheet>>,
+    mut query: Query<AsepriteSprite>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    mut misconfigured_event_writer: EventWriter<AnimationMisconfigured>,
+) {
+    let changed: HashSet<AssetId<AsepriteSheet>> = asset_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, handle, mut image, mut texture_atlas, mut animation_list) in query.iter_mut() {
+        if !handle.is_changed() && !changed.contains(&handle.0.id()) {
+            continue;
+        }
+        let Some(sheet) = sheets.get(&handle.0) else {
+            continue;
+        };
+        *image = sheet.image.clone();
+        texture_atlas.layout = sheet.layout.clone();
+        texture_atlas.index = 0;
+
+        // Animations the entity set up keep their playback speed and velocities
+        for (state, state_animation) in animation_list.0.iter_mut() {
+            let Some(sheet_animation) = sheet.sheet_animation(state, &state_animation.sheet_frames)
+            else {
+                if let SheetFrames::Tag(tag) | SheetFrames::ReversedTag(tag) =
+                    &state_animation.sheet_frames
+                {
+                    let error = AnimationError::MissingTag(tag.clone());
+                    error!(
+                        "{:?} animation of {:?} is misconfigured: {}",
+                        state, entity, error
+                    );
+                    misconfigured_event_writer.send(AnimationMisconfigured {
+                        entity,
+                        state: state.clone(),
+                        error,
+                    });
+                }
+                continue;
+            };
+            if state_animation.has_velocity_list() {
+                state_animation.indexes = sheet_animation.indexes;
+            } else {
+                let playback_speed = state_animation.playback_speed;
+                *state_animation = sheet_animation.with_playback_speed(playback_speed);
+            }
+        }
+        // The rest of the tags animate their states as they are
+        for tag in sheet.tags.iter() {
+            let state = GraphicsState::from_tag(&tag.name);
+            animation_list
+                .0
+                .entry(state)
+                .or_insert_with(|| sheet.tag_animation(tag));
+        }
+        report_misconfigured_animations(
+            entity,
+            &animation_list,
+            layouts
+                .get(&sheet.layout)
+                .map(|layout| layout.textures.len()),
+            &mut misconfigured_event_writer,
+        );
+    }
+}
+
+/// Checks newly inserted [`AnimationList`]s against their atlas.
+fn validate_animation_lists(
+    query: Query<(Entity, &AnimationList, Option<&TextureAtlas>), Added<AnimationList>>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    mut misconfigured_event_writer: EventWriter<AnimationMisconfigured>,
+) {
+    for (entity, animation_list, texture_atlas) in query.iter() {
+        let atlas_len = texture_atlas
+            .and_then(|texture_atlas| layouts.get(&texture_atlas.layout))
+            .map(|layout| layout.textures.len());
+        report_misconfigured_animations(
+            entity,
+            animation_list,
+            atlas_len,
+            &mut misconfigured_event_writer,
+        );
+    }
+}
+
+/// Logs and sends an [`AnimationMisconfigured`] for every animation that can't play as configured.
+fn report_misconfigured_animations(
+    entity: Entity,
+    animation_list: &AnimationList,
+    atlas_len: Option<usize>,
+    misconfigured_event_writer: &mut EventWriter<AnimationMisconfigured>,
+) {
+    for (state, state_animation) in animation_list.0.iter() {
+        if let Err(error) = state_animation.validate(atlas_len) {
+            error!(
+                "{:?} animation of {:?} is misconfigured: {}",
+                state, entity, error
+            );
+            misconfigured_event_writer.send(AnimationMisconfigured {
+                entity,
+                state: state.clone(),
+                error,
+            });
+        }
+    }
+}
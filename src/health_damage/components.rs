@@ -1,6 +1,8 @@
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
-use bevy::prelude::*;
+use bevy::{color::Mix, prelude::*};
+
+use crate::{GraphicsState, StatusEffect};
 
 #[derive(Component)]
 pub struct Enemy;
@@ -15,6 +17,92 @@ pub struct HealthbarBorder;
 #[derive(Component)]
 pub struct HealthbarFill;
 
+/// The part of a healthbar that shows recently lost health before draining down to the fill
+#[derive(Component)]
+pub struct HealthbarTrail {
+    pub percent: f32,
+    /// The health percent when it last changed, to tell when the entity is hit again
+    pub last_percent: f32,
+    pub delay: Timer,
+    /// How much of the bar drains away per second
+    pub speed: f32,
+}
+
+/// A divider between the segments of a healthbar
+#[derive(Component)]
+pub struct HealthbarPip;
+
+/// Shows lost health for `delay` before it drains away at `speed` bars per second
+#[derive(Clone, Debug)]
+pub struct HealthbarTrailStyle {
+    pub color: Color,
+    pub delay: Duration,
+    pub speed: f32,
+}
+
+/// How an entity's healthbar looks
+#[derive(Component, Clone, Debug)]
+pub struct HealthbarStyle {
+    /// Fill colours at health percents from 0 to 1, blended in between
+    pub gradient: Vec<(f32, Color)>,
+    pub trail: Option<HealthbarTrailStyle>,
+    /// How many segments the bar is split into
+    pub segments: Option<u32>,
+}
+
+impl HealthbarStyle {
+    pub fn new(color: Color) -> Self {
+        Self {
+            gradient: vec![(0., color)],
+            trail: None,
+            segments: None,
+        }
+    }
+    pub fn with_gradient(mut self, gradient: Vec<(f32, Color)>) -> Self {
+        self.gradient = gradient;
+        self.gradient.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        self
+    }
+    pub fn with_trail(mut self, color: Color, delay: u64, speed: f32) -> Self {
+        self.trail = Some(HealthbarTrailStyle {
+            color,
+            delay: Duration::from_millis(delay),
+            speed,
+        });
+        self
+    }
+    pub fn with_segments(mut self, segments: u32) -> Self {
+        self.segments = Some(segments);
+        self
+    }
+    /// The fill colour at `percent` health
+    pub fn color(&self, percent: f32) -> Color {
+        let Some(first) = self.gradient.first() else {
+            return Color::linear_rgb(1., 0., 0.);
+        };
+        let mut color = first.1;
+        for window in self.gradient.windows(2) {
+            let ((from_percent, from), (to_percent, to)) = (window[0], window[1]);
+            if percent >= to_percent {
+                color = to;
+            } else if percent > from_percent {
+                let fraction = (percent - from_percent) / (to_percent - from_percent);
+                color = LinearRgba::from(from)
+                    .mix(&LinearRgba::from(to), fraction)
+                    .into();
+                break;
+            }
+        }
+        color
+    }
+}
+
+impl Default for HealthbarStyle {
+    fn default() -> Self {
+        Self::new(Color::linear_rgb(1., 0., 0.))
+    }
+}
+
 #[derive(Component)]
 pub struct Health {
     pub current: f32,
@@ -33,50 +121,205 @@ impl Health {
     }
 }
 
-#[derive(Component)]
-pub struct HealingCurve(pub CubicSegment<Vec2>);
+/// What dealt the damage, each can be resisted separately
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DamageType {
+    CONTACT,
+    SWING,
+    POISON,
+    FIRE,
+}
 
-#[derive(Component)]
-pub struct HealingTimer(pub Timer);
+/// Asks for `amount` of `kind` damage to be dealt to `target`, before its [`Resistances`]
+#[derive(Event, Clone, Debug)]
+pub struct DamageEvent {
+    /// The entity that dealt the damage, `None` for the environment
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub amount: f32,
+    pub kind: DamageType,
+    /// Damage over time ignores invulnerability and doesn't cause any
+    pub periodic: bool,
+}
 
-impl HealingTimer {
-    fn new(duration: u64) -> Self {
-        Self(Timer::new(Duration::from_millis(duration), TimerMode::Once))
+/// An event sent when damage actually lowered an entity's [`Health`]
+#[derive(Event, Clone, Debug)]
+pub struct DamageApplied {
+    pub source: Option<Entity>,
+    pub target: Entity,
+    /// The damage dealt after resistances
+    pub amount: f32,
+    pub kind: DamageType,
+}
+
+/// Multiplies incoming damage per [`DamageType`], 0 for immune, missing types take full damage
+#[derive(Component, Default, Debug)]
+pub struct Resistances(pub HashMap<DamageType, f32>);
+
+impl Resistances {
+    pub fn multiplier(&self, kind: DamageType) -> f32 {
+        self.0.get(&kind).copied().unwrap_or(1.)
     }
 }
 
-impl HealingCurve {
-    pub fn new(control1: Vec2, control2: Vec2) -> Self {
-        Self(CubicSegment::new_bezier(control1, control2))
+/// How long an entity can't be damaged again after being hit
+#[derive(Component, Default, Debug)]
+pub struct InvulnerabilityDuration(pub Duration);
+
+/// A marker component for entities that were just hit and ignore damage until the timer finishes
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Invulnerable(pub Timer);
+
+/// Damages the [`Health`] characters overlapping this entity's collider, hitting each of them at most
+/// once every `interval`. Items only do it while they're being used
+#[derive(Component, Debug)]
+pub struct ContactDamage {
+    pub amount: f32,
+    pub kind: DamageType,
+    pub interval: Duration,
+    /// A status effect applied along with every hit
+    pub effect: Option<StatusEffect>,
+    /// The targets that were hit too recently to be hit again
+    pub recent_hits: HashMap<Entity, Timer>,
+}
+
+impl ContactDamage {
+    pub fn new(amount: f32, interval: u64) -> Self {
+        Self {
+            amount,
+            kind: DamageType::CONTACT,
+            interval: Duration::from_millis(interval),
+            effect: None,
+            recent_hits: HashMap::new(),
+        }
+    }
+    pub fn with_kind(mut self, kind: DamageType) -> Self {
+        self.kind = kind;
+        self
+    }
+    pub fn with_effect(mut self, effect: StatusEffect) -> Self {
+        self.effect = Some(effect);
+        self
     }
 }
 
-impl Default for HealingCurve {
-    fn default() -> Self {
-        Self::new(Vec2::new(0., 0.), Vec2::new(1., 1.))
+/// A marker component for entities whose [`Health`] ran out, until their [`DeathPolicy`] is carried out
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Dead;
+
+/// An event sent when an entity's [`Health`] runs out
+#[derive(Event, Clone, Debug)]
+pub struct Died {
+    pub entity: Entity,
+    /// The source of the damage that killed it, if there was one
+    pub killer: Option<Entity>,
+}
+
+/// What happens to an entity once it's [`Dead`]
+#[derive(Component, Clone, Debug, Default)]
+pub enum DeathPolicy {
+    /// Despawns the entity and its [`Healthbar`]
+    #[default]
+    Despawn,
+    /// Plays the state's animation once through, then despawns. Entities without a timed
+    /// animation for the state despawn straight away
+    Animate(GraphicsState),
+    /// Comes back at its [`SpawnPoint`] with full health
+    Respawn,
+}
+
+/// Where a [`DeathPolicy::Respawn`] entity comes back, the position it first spawned at until a
+/// checkpoint moves it. In world space, whatever the entity is parented to
+#[derive(Component, Default, Debug)]
+pub struct SpawnPoint(pub Option<Vec2>);
+
+/// Heals `rate` health per second, up to `cap` as a fraction of max health,
+/// once `delay` has passed since the entity was last hit
+#[derive(Component, Debug)]
+pub struct Regeneration {
+    pub enabled: bool,
+    pub rate: f32,
+    pub cap: f32,
+    pub delay: Timer,
+}
+
+impl Regeneration {
+    pub fn new(rate: f32, delay: u64, cap: f32) -> Self {
+        let delay = Duration::from_millis(delay);
+        let mut delay_timer = Timer::new(delay, TimerMode::Once);
+        // Nothing has hit the entity yet
+        delay_timer.set_elapsed(delay);
+        Self {
+            enabled: true,
+            rate,
+            cap: cap.clamp(0., 1.),
+            delay: delay_timer,
+        }
+    }
+    /// Regeneration that never heals
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::new(0., 0, 0.)
+        }
+    }
+    /// The health regeneration stops at
+    pub fn limit(&self, health: &Health) -> f32 {
+        health.max * self.cap
     }
 }
 
 #[derive(Bundle)]
 pub struct HealthBundle {
     pub health: Health,
-    pub healing_curve: HealingCurve,
-    pub healing_timer: HealingTimer,
+    pub regeneration: Regeneration,
+    pub resistances: Resistances,
+    pub invulnerability_duration: InvulnerabilityDuration,
+    pub death_policy: DeathPolicy,
+    pub spawn_point: SpawnPoint,
+    pub healthbar_style: HealthbarStyle,
 }
 
 impl HealthBundle {
-    pub fn new(max: f32, heal_curve: [[f32; 2]; 2], heal_curve_duration: u64) -> Self {
+    /// Full health of `max` that doesn't regenerate
+    pub fn new(max: f32) -> Self {
         Self {
             health: Health::new(max),
-            healing_curve: HealingCurve(CubicSegment::new_bezier(
-                Vec2::from_array(heal_curve[0]),
-                Vec2::from_array(heal_curve[1]),
-            )),
-            healing_timer: HealingTimer::new(heal_curve_duration),
+            regeneration: Regeneration::disabled(),
+            resistances: Resistances::default(),
+            invulnerability_duration: InvulnerabilityDuration::default(),
+            death_policy: DeathPolicy::default(),
+            spawn_point: SpawnPoint::default(),
+            healthbar_style: HealthbarStyle::default(),
         }
     }
     pub fn with_current_health(mut self, health: f32) -> Self {
         self.health.current = health;
         self
     }
+    /// Regenerates `rate` health per second up to `cap` of max health, `delay` milliseconds
+    /// after the last hit
+    pub fn with_regeneration(mut self, rate: f32, delay: u64, cap: f32) -> Self {
+        self.regeneration = Regeneration::new(rate, delay, cap);
+        self
+    }
+    /// Multiplies `kind` damage by `multiplier`
+    pub fn with_resistance(mut self, kind: DamageType, multiplier: f32) -> Self {
+        self.resistances.0.insert(kind, multiplier);
+        self
+    }
+    pub fn with_invulnerability(mut self, duration: u64) -> Self {
+        self.invulnerability_duration.0 = Duration::from_millis(duration);
+        self
+    }
+    pub fn with_death_policy(mut self, death_policy: DeathPolicy) -> Self {
+        self.death_policy = death_policy;
+        self
+    }
+    pub fn with_healthbar_style(mut self, healthbar_style: HealthbarStyle) -> Self {
+        self.healthbar_style = healthbar_style;
+        self
+    }
 }
@@ -1,38 +1,78 @@
-use avian2d::prelude::PhysicsSet;
-use bevy::{prelude::*, sprite::Anchor};
+use avian2d::{
+    collision::AnyCollider,
+    math::Vector,
+    prelude::{
+        Collider, LinearVelocity, Position, Rotation, ShapeCaster, SpatialQuery, SpatialQueryFilter,
+    },
+};
+use bevy::{
+    prelude::*,
+    sprite::Anchor,
+    utils::{HashMap, HashSet},
+};
 
-use crate::{HealingCurve, HealingTimer, Health, Healthbar, HealthbarBorder, HealthbarFill};
+use crate::{
+    interpolate_transform, AirDashCounter, AnimTimer, AnimationFinished, AnimationList,
+    ApplyStatusEffect, ContactDamage, CrouchShapes, Crouching, DamageApplied, DamageEvent,
+    DamageType, Dead, DeathPolicy, Died, DropThrough, Equipped, GameLayer, GraphicsStateOverride,
+    Health, Healthbar, HealthbarBorder, HealthbarFill, HealthbarPip, HealthbarStyle,
+    HealthbarTrail, Hitstun, InUse, InvulnerabilityDuration, Invulnerable, Item, JumpFallCounter,
+    JumpFallState, KnockbackEvent, MoveState, PlaybackMode, PreviousPosition, Regeneration,
+    Resistances, SpawnPoint, Submerged, SwingDesc, TouchingWall, UseTime, WallCaster,
+};
 
 pub struct HealthPlugin;
 
 impl Plugin for HealthPlugin {
     fn build(&self, app: &mut App) {
-        app
+        app.add_event::<DamageEvent>()
+            .add_event::<DamageApplied>()
+            .add_event::<Died>()
             //.add_systems(Startup, (spawn_health_bars.after(setup)))
+            .add_systems(
+                Update,
+                (
+                    record_spawn_points,
+                    tick_invulnerability,
+                    contact_damage,
+                    apply_damage,
+                    knock_back_hits,
+                    detect_deaths,
+                    report_deaths,
+                    apply_death_policies,
+                    respawn_dead,
+                    finish_death_animations,
+                    interrupt_regeneration,
+                    regenerate,
+                )
+                    .chain(),
+            )
             .add_systems(
                 Update,
                 (
                     health_bar_follow_entity
-                        .after(PhysicsSet::Sync)
+                        .after(interpolate_transform)
                         .before(TransformSystem::TransformPropagate),
                     spawn_health_bars,
+                    despawn_orphaned_health_bars,
                     display_current_health.after(health_bar_follow_entity),
+                    drain_health_bar_trails,
                     hide_full_hp_bar,
-                    healing,
                 ),
             );
     }
 }
+const HEALTHBAR_SIZE: Vec2 = Vec2::new(28., 5.);
+
 fn spawn_health_bars(
-    mut query: Query<(Entity, &Transform, &Name), Added<Health>>,
+    mut query: Query<(Entity, &Transform, Option<&HealthbarStyle>), Added<Health>>,
     asset_server: Res<AssetServer>,
     mut commands: Commands,
 ) {
-    for (entity, transform, name) in query.iter_mut() {
+    for (entity, transform, style) in query.iter_mut() {
+        let style = style.cloned().unwrap_or_default();
         let mut z_ordered_transform = *transform;
         z_ordered_transform.translation.z = 110.;
-        let mut z_ordered_transform_offset = *transform;
-        z_ordered_transform_offset.translation.x = -14.;
         commands
             .spawn((
                 Healthbar(entity),
@@ -50,47 +90,172 @@ fn spawn_health_bars(
                     HealthbarFill,
                     SpriteBundle {
                         sprite: Sprite {
-                            color: Color::linear_rgb(1., 0., 0.),
-                            custom_size: Some(Vec2::new(28., 5.)),
+                            color: style.color(1.),
+                            custom_size: Some(HEALTHBAR_SIZE),
                             anchor: Anchor::CenterLeft,
                             ..default()
                         },
-                        transform: Transform::from_xyz(-14., 0., -1.),
-                        //transform: *transform,
+                        transform: Transform::from_xyz(-HEALTHBAR_SIZE.x / 2., 0., -1.),
                         ..default()
                     },
                 ));
+                if let Some(trail) = &style.trail {
+                    healthbar_parent.spawn((
+                        HealthbarTrail {
+                            percent: 1.,
+                            last_percent: 1.,
+                            delay: Timer::new(trail.delay, TimerMode::Once),
+                            speed: trail.speed,
+                        },
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: trail.color,
+                                custom_size: Some(HEALTHBAR_SIZE),
+                                anchor: Anchor::CenterLeft,
+                                ..default()
+                            },
+                            transform: Transform::from_xyz(-HEALTHBAR_SIZE.x / 2., 0., -2.),
+                            ..default()
+                        },
+                    ));
+                }
+                let segments = style.segments.unwrap_or(1).max(1);
+                for segment in 1..segments {
+                    healthbar_parent.spawn((
+                        HealthbarPip,
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: Color::BLACK,
+                                custom_size: Some(Vec2::new(1., HEALTHBAR_SIZE.y)),
+                                ..default()
+                            },
+                            transform: Transform::from_xyz(
+                                HEALTHBAR_SIZE.x * (segment as f32 / segments as f32 - 0.5),
+                                0.,
+                                -0.5,
+                            ),
+                            ..default()
+                        },
+                    ));
+                }
             });
     }
 }
 
+/// Despawns healthbars whose entity is gone or no longer has [`Health`].
+fn despawn_orphaned_health_bars(
+    mut commands: Commands,
+    bar_query: Query<(Entity, &Healthbar)>,
+    health_query: Query<(), With<Health>>,
+) {
+    for (bar_entity, healthbar) in bar_query.iter() {
+        if !health_query.contains(healthbar.0) {
+            commands.entity(bar_entity).despawn_recursive();
+        }
+    }
+}
+
+/// Half the height of an entity, from its collider, or its sprite's atlas frame or size.
+fn half_height(
+    transform: &Transform,
+    collider: Option<&Collider>,
+    texture_atlas: Option<&TextureAtlas>,
+    sprite: Option<&Sprite>,
+    layouts: &Assets<TextureAtlasLayout>,
+) -> f32 {
+    if let Some(collider) = collider {
+        return collider.aabb(Vector::ZERO, Rotation::default()).size().y / 2.;
+    }
+    let frame_height = texture_atlas
+        .and_then(|texture_atlas| {
+            layouts
+                .get(&texture_atlas.layout)
+                .and_then(|layout| layout.textures.get(texture_atlas.index))
+        })
+        .map(|frame| frame.height() as f32)
+        .or_else(|| {
+            sprite
+                .and_then(|sprite| sprite.custom_size)
+                .map(|size| size.y)
+        })
+        .unwrap_or(0.);
+    frame_height * transform.scale.y / 2.
+}
+
+type HealthbarOwner<'a> = (
+    &'a Transform,
+    Option<&'a Collider>,
+    Option<&'a TextureAtlas>,
+    Option<&'a Sprite>,
+);
+
 fn health_bar_follow_entity(
     mut bar_query: Query<(&Healthbar, &mut Transform), Without<Health>>,
-    health_query: Query<
-        (&Transform, &Handle<Image>),
-        (With<Sprite>, With<Health>, Without<Healthbar>),
-    >,
-    assets: Res<Assets<Image>>,
+    health_query: Query<HealthbarOwner, (With<Health>, Without<Healthbar>)>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
 ) {
     for (bar_linked_entity, mut bar_transform) in bar_query.iter_mut() {
-        let (health_transform, health_sprite_handle) =
-            health_query.get(bar_linked_entity.0).unwrap();
-        let health_sprite_height = assets.get(health_sprite_handle).unwrap().size().y as f32;
+        let Ok((health_transform, collider, texture_atlas, sprite)) =
+            health_query.get(bar_linked_entity.0)
+        else {
+            continue;
+        };
+        let half_height = half_height(health_transform, collider, texture_atlas, sprite, &layouts);
         bar_transform.translation.x = health_transform.translation.x;
-        bar_transform.translation.y =
-            health_transform.translation.y + health_sprite_height / 2. + 10.;
+        bar_transform.translation.y = health_transform.translation.y + half_height + 10.;
     }
 }
 
 fn display_current_health(
     bar_query: Query<&Healthbar>,
-    mut bar_fill_query: Query<(&mut Transform, &Parent), With<HealthbarFill>>,
+    mut bar_fill_query: Query<(&mut Transform, &mut Sprite, &Parent), With<HealthbarFill>>,
+    health_query: Query<(&Health, Option<&HealthbarStyle>)>,
+) {
+    for (mut transform, mut sprite, bar_entity) in bar_fill_query.iter_mut() {
+        let Some((health, style)) = bar_query
+            .get(bar_entity.get())
+            .ok()
+            .and_then(|healthbar| health_query.get(healthbar.0).ok())
+        else {
+            continue;
+        };
+        let percent = health.percent().clamp(0., 1.);
+        transform.scale.x = percent;
+        if let Some(style) = style {
+            sprite.color = style.color(percent);
+        }
+    }
+}
+
+/// Holds the trail at the health from before a hit for its delay, then drains it down to the fill.
+fn drain_health_bar_trails(
+    bar_query: Query<&Healthbar>,
+    mut trail_query: Query<(&mut Transform, &mut HealthbarTrail, &Parent)>,
     health_query: Query<&Health>,
+    time: Res<Time>,
 ) {
-    for (mut transform, bar_entity) in bar_fill_query.iter_mut() {
-        let health_entity = bar_query.get(bar_entity.get()).unwrap().0;
-        let health = health_query.get(health_entity).unwrap();
-        transform.scale.x = health.percent();
+    for (mut transform, mut trail, bar_entity) in trail_query.iter_mut() {
+        let Some(health) = bar_query
+            .get(bar_entity.get())
+            .ok()
+            .and_then(|healthbar| health_query.get(healthbar.0).ok())
+        else {
+            continue;
+        };
+        let percent = health.percent().clamp(0., 1.);
+        if percent < trail.last_percent {
+            trail.delay.reset();
+        }
+        trail.last_percent = percent;
+        if trail.percent <= percent {
+            trail.percent = percent;
+        } else {
+            trail.delay.tick(time.delta());
+            if trail.delay.finished() {
+                trail.percent = (trail.percent - trail.speed * time.delta_seconds()).max(percent);
+            }
+        }
+        transform.scale.x = trail.percent;
     }
 }
 
@@ -99,7 +264,9 @@ fn hide_full_hp_bar(
     health_query: Query<&Health>,
 ) {
     for (health_entity, mut visibility) in bar_query.iter_mut() {
-        let health = health_query.get(health_entity.0).unwrap();
+        let Ok(health) = health_query.get(health_entity.0) else {
+            continue;
+        };
         if health.is_full() {
             *visibility = Visibility::Hidden;
         } else {
@@ -108,24 +275,419 @@ fn hide_full_hp_bar(
     }
 }
 
-fn healing(mut query: Query<(&mut Health, &HealingCurve, &mut HealingTimer)>, time: Res<Time>) {
-    for (mut health, heal_curve, mut heal_timer) in query.iter_mut() {
-        if !health.is_full() {
-            heal_timer.0.tick(time.delta());
-            health.current = health.max * heal_curve.0.ease(heal_timer.0.fraction());
+/// Holds off [`Regeneration`] for its delay after every hit.
+fn interrupt_regeneration(
+    mut query: Query<&mut Regeneration>,
+    mut damage_applied_event_reader: EventReader<DamageApplied>,
+) {
+    for damage_applied in damage_applied_event_reader.read() {
+        if let Ok(mut regeneration) = query.get_mut(damage_applied.target) {
+            regeneration.delay.reset();
         }
-        //info!("Health Old: {}", health.current);
-        //info!("Health Ease: {}", healing_curve.0.ease(health.percent()));
-        //health.current = health.max
-        //    * healing_curve
-        //        .0
-        //        .ease(health.percent() * time.delta_seconds());
-        //health.current += 1. * time.delta_seconds();
-        health.current = if health.current >= health.max {
-            health.max
-        } else {
-            health.current
+    }
+}
+
+/// Heals entities at their [`Regeneration`] rate, never past its cap or their max health.
+fn regenerate(mut query: Query<(&mut Health, &mut Regeneration), Without<Dead>>, time: Res<Time>) {
+    for (mut health, mut regeneration) in query.iter_mut() {
+        if !regeneration.enabled {
+            continue;
+        }
+        regeneration.delay.tick(time.delta());
+        let limit = regeneration.limit(&health).min(health.max);
+        if !regeneration.delay.finished() || health.current >= limit {
+            continue;
+        }
+        health.current = (health.current + regeneration.rate * time.delta_seconds()).min(limit);
+    }
+}
+
+/// Lets hit entities take damage again once their invulnerability runs out.
+fn tick_invulnerability(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Invulnerable)>,
+    time: Res<Time>,
+) {
+    for (entity, mut invulnerable) in query.iter_mut() {
+        invulnerable.0.tick(time.delta());
+        if invulnerable.0.finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+}
+
+type DamageDealer<'a> = (
+    Entity,
+    &'a mut ContactDamage,
+    (&'a Collider, &'a Position, &'a Rotation),
+    (Has<Item>, Has<InUse>, Option<&'a Equipped>),
+);
+
+/// Damages every [`Health`] character overlapping something with [`ContactDamage`], hitting each
+/// of them at most once every [`ContactDamage::interval`] no matter the frame rate, and applies
+/// its [`ContactDamage::effect`] with every hit. Items only hit while in use, and never whoever
+/// holds them.
+fn contact_damage(
+    spatial_query: SpatialQuery,
+    mut query: Query<DamageDealer, Without<Dead>>,
+    health_query: Query<Has<ContactDamage>, With<Health>>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+    mut apply_status_effect_event_writer: EventWriter<ApplyStatusEffect>,
+    time: Res<Time>,
+) {
+    for (entity, mut contact_damage, (collider, position, rotation), (is_item, in_use, equipped)) in
+        query.iter_mut()
+    {
+        contact_damage
+            .recent_hits
+            .retain(|_, timer| !timer.tick(time.delta()).finished());
+        if is_item && !in_use {
+            continue;
+        }
+        // Characters don't collide with each other, so look for overlaps instead
+        let filter =
+            SpatialQueryFilter::from_mask(GameLayer::CHARACTER).with_excluded_entities([entity]);
+        for target in
+            spatial_query.shape_intersections(collider, position.0, rotation.as_radians(), filter)
+        {
+            // Things that deal contact damage don't hurt each other, but items hurt anything
+            let Ok(deals_contact_damage) = health_query.get(target) else {
+                continue;
+            };
+            if (deals_contact_damage && !is_item)
+                || equipped.is_some_and(|equipped| equipped.0 == target)
+                || contact_damage.recent_hits.contains_key(&target)
+            {
+                continue;
+            }
+            let cooldown = Timer::new(contact_damage.interval, TimerMode::Once);
+            contact_damage.recent_hits.insert(target, cooldown);
+            damage_event_writer.send(DamageEvent {
+                source: Some(entity),
+                target,
+                amount: contact_damage.amount,
+                kind: contact_damage.kind,
+                periodic: false,
+            });
+            if let Some(effect) = &contact_damage.effect {
+                apply_status_effect_event_writer.send(ApplyStatusEffect {
+                    target,
+                    source: Some(entity),
+                    effect: effect.clone(),
+                });
+            }
+        }
+    }
+}
+
+type DamageTarget<'a> = (
+    &'a mut Health,
+    Option<&'a Resistances>,
+    Option<&'a InvulnerabilityDuration>,
+    Has<Invulnerable>,
+    Has<Dead>,
+);
+
+/// Lowers [`Health`] by the damage left after [`Resistances`], then makes the target
+/// [`Invulnerable`] for its [`InvulnerabilityDuration`] unless the damage is periodic.
+fn apply_damage(
+    mut commands: Commands,
+    mut query: Query<DamageTarget>,
+    mut damage_event_reader: EventReader<DamageEvent>,
+    mut damage_applied_event_writer: EventWriter<DamageApplied>,
+) {
+    // Invulnerable isn't inserted until the commands run, so track this frame's hits here
+    let mut hit_this_frame = HashSet::new();
+    for damage_event in damage_event_reader.read() {
+        let Ok((mut health, resistances, invulnerability_duration, invulnerable, dead)) =
+            query.get_mut(damage_event.target)
+        else {
+            continue;
         };
-        //info!("Health New: {}\n", health.current);
+        if dead
+            || (!damage_event.periodic
+                && (invulnerable || hit_this_frame.contains(&damage_event.target)))
+        {
+            continue;
+        }
+        let amount = damage_event.amount
+            * resistances.map_or(1., |resistances| resistances.multiplier(damage_event.kind));
+        if amount <= 0. {
+            continue;
+        }
+        health.current = (health.current - amount).max(0.);
+        if let Some(invulnerability_duration) = invulnerability_duration {
+            if !damage_event.periodic && !invulnerability_duration.0.is_zero() {
+                hit_this_frame.insert(damage_event.target);
+                commands
+                    .entity(damage_event.target)
+                    .insert(Invulnerable(Timer::new(
+                        invulnerability_duration.0,
+                        TimerMode::Once,
+                    )));
+            }
+        }
+        damage_applied_event_writer.send(DamageApplied {
+            source: damage_event.source,
+            target: damage_event.target,
+            amount,
+            kind: damage_event.kind,
+        });
+    }
+}
+
+/// Knocks characters away from whatever touched or swung at them, harder the more damage it did.
+fn knock_back_hits(
+    positions: Query<&Position>,
+    mut damage_applied_event_reader: EventReader<DamageApplied>,
+    mut knockback_event_writer: EventWriter<KnockbackEvent>,
+) {
+    for damage_applied in damage_applied_event_reader.read() {
+        if !matches!(damage_applied.kind, DamageType::CONTACT | DamageType::SWING) {
+            continue;
+        }
+        let Some(source) = damage_applied.source else {
+            continue;
+        };
+        let (Ok(source_position), Ok(target_position)) =
+            (positions.get(source), positions.get(damage_applied.target))
+        else {
+            continue;
+        };
+        let direction = Dir2::new(target_position.0 - source_position.0).unwrap_or(Dir2::Y);
+        knockback_event_writer.send(KnockbackEvent::new(
+            damage_applied.target,
+            direction,
+            damage_applied.amount * 30.,
+            200,
+        ));
+    }
+}
+
+/// Remembers where [`DeathPolicy::Respawn`] entities first spawned, from their world [`Position`].
+fn record_spawn_points(mut query: Query<(&DeathPolicy, &mut SpawnPoint, &Position)>) {
+    for (death_policy, mut spawn_point, position) in query.iter_mut() {
+        if matches!(death_policy, DeathPolicy::Respawn) && spawn_point.0.is_none() {
+            spawn_point.0 = Some(position.0);
+        }
+    }
+}
+
+/// Marks entities whose [`Health`] ran out as [`Dead`], crediting the last damage that hit them.
+fn detect_deaths(
+    mut commands: Commands,
+    query: Query<(Entity, &Health), Without<Dead>>,
+    mut damage_applied_event_reader: EventReader<DamageApplied>,
+    mut died_event_writer: EventWriter<Died>,
+) {
+    let killers: HashMap<Entity, Option<Entity>> = damage_applied_event_reader
+        .read()
+        .map(|damage_applied| (damage_applied.target, damage_applied.source))
+        .collect();
+    for (entity, health) in query.iter() {
+        if health.current > 0. {
+            continue;
+        }
+        commands.entity(entity).insert(Dead);
+        died_event_writer.send(Died {
+            entity,
+            killer: killers.get(&entity).copied().flatten(),
+        });
+    }
+}
+
+/// Logs every death along with its killer.
+fn report_deaths(names: Query<&Name>, mut died_event_reader: EventReader<Died>) {
+    let name = |entity: Entity| {
+        names
+            .get(entity)
+            .map_or_else(|_| format!("{:?}", entity), |name| name.to_string())
+    };
+    for died in died_event_reader.read() {
+        match died.killer {
+            Some(killer) => info!("{} was killed by {}", name(died.entity), name(killer)),
+            None => info!("{} died", name(died.entity)),
+        }
+    }
+}
+
+/// Despawns newly [`Dead`] entities or starts their death animation, played once so it can finish.
+fn apply_death_policies(
+    mut commands: Commands,
+    mut query: Query<(Entity, &DeathPolicy, Option<&mut AnimationList>), Added<Dead>>,
+) {
+    for (entity, death_policy, animation_list) in query.iter_mut() {
+        match death_policy {
+            DeathPolicy::Despawn => commands.entity(entity).despawn_recursive(),
+            DeathPolicy::Animate(state) => {
+                // Velocity lists never finish, so they can't be waited on
+                let timer = animation_list
+                    .and_then(|animation_list| animation_list.into_inner().0.get_mut(state))
+                    .and_then(|state_animation| match &mut state_animation.anim_timer {
+                        AnimTimer::AnimTimer(timer) => Some(timer),
+                        AnimTimer::AnimVelocityList(_) => None,
+                    });
+                match timer {
+                    Some(timer) => {
+                        timer.mode = PlaybackMode::Once;
+                        commands
+                            .entity(entity)
+                            .insert(GraphicsStateOverride(state.clone()));
+                    }
+                    None => commands.entity(entity).despawn_recursive(),
+                }
+            }
+            DeathPolicy::Respawn => {}
+        }
+    }
+}
+
+/// Despawns [`DeathPolicy::Animate`] entities once their death animation has played.
+fn finish_death_animations(
+    mut commands: Commands,
+    query: Query<&DeathPolicy, With<Dead>>,
+    mut animation_finished_event_reader: EventReader<AnimationFinished>,
+) {
+    for animation_finished in animation_finished_event_reader.read() {
+        if let Ok(DeathPolicy::Animate(state)) = query.get(animation_finished.entity) {
+            if *state == animation_finished.state {
+                commands
+                    .entity(animation_finished.entity)
+                    .despawn_recursive();
+            }
+        }
+    }
+}
+
+type RespawningEntity<'a> = (
+    Entity,
+    &'a DeathPolicy,
+    &'a SpawnPoint,
+    &'a mut Health,
+    (&'a mut Transform, Option<&'a Parent>),
+    Option<&'a InvulnerabilityDuration>,
+    (
+        Option<&'a mut Position>,
+        Option<&'a mut PreviousPosition>,
+        Option<&'a mut LinearVelocity>,
+    ),
+    (
+        Option<&'a mut JumpFallCounter>,
+        Option<&'a mut AirDashCounter>,
+    ),
+    (Option<&'a mut MoveState>, Option<&'a mut JumpFallState>),
+    (
+        Option<&'a CrouchShapes>,
+        Option<&'a mut Collider>,
+        Option<&'a mut ShapeCaster>,
+        Option<&'a mut WallCaster>,
+    ),
+);
+
+/// Brings [`DeathPolicy::Respawn`] entities back at their [`SpawnPoint`] with full health,
+/// standing, their jumps and dashes back and their equipped items at rest.
+fn respawn_dead(
+    mut commands: Commands,
+    parents: Query<&GlobalTransform>,
+    mut query: Query<RespawningEntity, (With<Dead>, Without<Item>)>,
+    mut item_query: Query<
+        (Entity, &Equipped, &SwingDesc, &mut Transform, &mut UseTime),
+        With<Item>,
+    >,
+) {
+    for (
+        entity,
+        death_policy,
+        spawn_point,
+        mut health,
+        (mut transform, parent),
+        invulnerability_duration,
+        (position, previous_position, linear_velocity),
+        (jump_fall_counter, air_dash_counter),
+        (move_state, jump_fall_state),
+        (crouch_shapes, collider, ground_caster, wall_caster),
+    ) in query.iter_mut()
+    {
+        if !matches!(death_policy, DeathPolicy::Respawn) {
+            continue;
+        }
+        health.current = health.max;
+        if let Some(spawn_point) = spawn_point.0 {
+            // The spawn point is in world space, the transform is relative to the parent
+            let translation = match parent.and_then(|parent| parents.get(parent.get()).ok()) {
+                Some(parent_transform) => parent_transform
+                    .affine()
+                    .inverse()
+                    .transform_point3(spawn_point.extend(0.)),
+                None => spawn_point.extend(0.),
+            };
+            transform.translation.x = translation.x;
+            transform.translation.y = translation.y;
+            if let Some(mut position) = position {
+                position.0 = spawn_point;
+            }
+            // Don't smooth the jump back to the spawn point
+            if let Some(mut previous_position) = previous_position {
+                previous_position.0 = None;
+            }
+        }
+        if let Some(mut linear_velocity) = linear_velocity {
+            linear_velocity.0 = Vec2::ZERO;
+        }
+        if let Some(mut jump_fall_counter) = jump_fall_counter {
+            jump_fall_counter.0 = 0;
+        }
+        if let Some(mut air_dash_counter) = air_dash_counter {
+            air_dash_counter.0 = 0;
+        }
+        // Don't come back mid-dash, mid-climb or mid-jump
+        if let Some(mut move_state) = move_state {
+            *move_state = MoveState::Idle;
+        }
+        if let Some(mut jump_fall_state) = jump_fall_state {
+            *jump_fall_state = JumpFallState::Falling;
+        }
+        // Nor crouched
+        if let Some(standing) = crouch_shapes.map(|crouch_shapes| &crouch_shapes.standing) {
+            if let Some(mut collider) = collider {
+                *collider = standing.collider.clone();
+            }
+            if let Some(mut ground_caster) = ground_caster {
+                ground_caster.shape = standing.ground_caster.clone();
+            }
+            if let Some(mut wall_caster) = wall_caster {
+                wall_caster.shape = standing.wall_caster.clone();
+            }
+        }
+        for (item_entity, equipped, swing_desc, mut item_transform, mut use_time) in
+            item_query.iter_mut()
+        {
+            if equipped.0 != entity {
+                continue;
+            }
+            use_time.0.reset();
+            item_transform.rotation = Quat::from_rotation_z(swing_desc.rest_angle);
+            commands.entity(item_entity).remove::<InUse>();
+        }
+
+        let mut entity_commands = commands.entity(entity);
+        entity_commands.remove::<(
+            Dead,
+            Hitstun,
+            Submerged,
+            DropThrough,
+            Crouching,
+            TouchingWall,
+            Invulnerable,
+            GraphicsStateOverride,
+        )>();
+        if let Some(invulnerability_duration) = invulnerability_duration {
+            if !invulnerability_duration.0.is_zero() {
+                entity_commands.insert(Invulnerable(Timer::new(
+                    invulnerability_duration.0,
+                    TimerMode::Once,
+                )));
+            }
+        }
     }
 }
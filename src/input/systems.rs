@@ -35,10 +35,18 @@ pub fn keyboard_input(
         let right_pressed = keyboard_input.any_pressed([KeyCode::KeyD, KeyCode::ArrowRight]);
         let jump_pressed = keyboard_input.any_just_pressed([KeyCode::Space]);
         let fall_pressed = keyboard_input.any_just_pressed([KeyCode::KeyS, KeyCode::ArrowDown]);
+        let dash_pressed = keyboard_input.any_just_pressed([KeyCode::ShiftLeft]);
+        let up_pressed = keyboard_input.any_pressed([KeyCode::KeyW, KeyCode::ArrowUp]);
+        let down_pressed = keyboard_input.any_pressed([KeyCode::KeyS, KeyCode::ArrowDown]);
+        let crouch_pressed = keyboard_input.any_just_pressed([KeyCode::KeyC, KeyCode::ControlLeft]);
 
         let left_released = keyboard_input.any_just_released([KeyCode::KeyA, KeyCode::ArrowLeft]);
         let right_released = keyboard_input.any_just_released([KeyCode::KeyD, KeyCode::ArrowRight]);
         let jump_released = keyboard_input.any_just_released([KeyCode::Space]);
+        let up_released = keyboard_input.any_just_released([KeyCode::KeyW, KeyCode::ArrowUp]);
+        let down_released = keyboard_input.any_just_released([KeyCode::KeyS, KeyCode::ArrowDown]);
+        let crouch_released =
+            keyboard_input.any_just_released([KeyCode::KeyC, KeyCode::ControlLeft]);
 
         if left_pressed && right_pressed {
             movement_event_writer.send(MovementEvent::new(player_entity, MovementAction::RunEnd));
@@ -67,6 +75,33 @@ pub fn keyboard_input(
         if fall_pressed {
             movement_event_writer.send(MovementEvent::new(player_entity, MovementAction::Fall));
         }
+        if dash_pressed {
+            movement_event_writer.send(MovementEvent::new(player_entity, MovementAction::Dash));
+        }
+
+        if up_pressed && down_pressed {
+            movement_event_writer.send(MovementEvent::new(player_entity, MovementAction::ClimbEnd));
+        } else {
+            if up_pressed {
+                movement_event_writer
+                    .send(MovementEvent::new(player_entity, MovementAction::ClimbUp));
+            }
+            if down_pressed {
+                movement_event_writer
+                    .send(MovementEvent::new(player_entity, MovementAction::ClimbDown));
+            }
+        }
+        if (up_released || down_released) && !(up_pressed || down_pressed) {
+            movement_event_writer.send(MovementEvent::new(player_entity, MovementAction::ClimbEnd));
+        }
+
+        if crouch_pressed {
+            movement_event_writer.send(MovementEvent::new(player_entity, MovementAction::Crouch));
+        }
+        if crouch_released {
+            movement_event_writer
+                .send(MovementEvent::new(player_entity, MovementAction::CrouchEnd));
+        }
         for (item_entity, equipped, in_use) in items.iter() {
             if equipped {
                 if keyboard_input.just_pressed(KeyCode::KeyQ) {
@@ -4,13 +4,11 @@ use avian2d::{collision::Collider, prelude::Sensor};
 use bevy::{math::NormedVectorSpace, prelude::*};
 use bevy_ecs_ldtk::prelude::*;
 
+use crate::{ContactDamage, DamageType};
+
 #[derive(Component)]
 pub struct Item;
 
-pub enum DamageType {
-    CONTACT,
-}
-
 #[derive(Component)]
 pub struct Equipped(pub Entity);
 
@@ -84,6 +82,7 @@ pub struct ItemBundle {
     pub collider: Collider,
     pub sensor: Sensor,
     pub swing_bundle: SwingBundle,
+    pub swing_damage: ContactDamage,
 }
 
 impl Default for ItemBundle {
@@ -110,6 +109,7 @@ impl Default for ItemBundle {
                 use_accel: UseAccel(CubicSegment::new_bezier((0.25, 0.1), (0.25, 1.0))),
                 use_time: UseTime(Timer::new(Duration::from_millis(10000), TimerMode::Once)),
             },
+            swing_damage: ContactDamage::new(10., 250).with_kind(DamageType::SWING),
         }
     }
 }
@@ -131,6 +131,10 @@ impl ItemBundle {
         self.swing_bundle.use_accel.0 = curve;
         self
     }
+    pub fn with_swing_damage(mut self, amount: f32) -> Self {
+        self.swing_damage.amount = amount;
+        self
+    }
     pub fn with_use_time(mut self, use_time: u64) -> Self {
         self.swing_bundle.use_time =
             UseTime(Timer::new(Duration::from_millis(use_time), TimerMode::Once));
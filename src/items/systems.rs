@@ -2,10 +2,10 @@ use std::{f32::consts::PI, ops::Deref};
 
 use super::prelude::*;
 use crate::{
-    process_player, Facing, GraphicsBundle, Interactable, InteractableItems, InteractorRange,
-    Player,
+    interpolate_transform, process_player, Facing, GraphicsBundle, Interactable, InteractableItems,
+    InteractorRange, Player,
 };
-use avian2d::{collision::CollidingEntities, prelude::PhysicsSet};
+use avian2d::collision::CollidingEntities;
 use bevy::{prelude::*, sprite::Anchor};
 use bevy_ecs_ldtk::EntityInstance;
 
@@ -21,7 +21,7 @@ impl Plugin for ItemPlugin {
                 use_item,
                 equip_item,
                 equipped_item_follow_player
-                    .after(PhysicsSet::Sync)
+                    .after(interpolate_transform)
                     .before(TransformSystem::TransformPropagate),
                 handle_item_actions,
             ),
@@ -70,7 +70,8 @@ fn process_item(
                         //.with_position(50.,-50.)
                         .with_use_accel(CubicSegment::new_bezier((0.25, 0.1), (0.25, 1.)))
                         .with_use_time(250)
-                        .with_swing_desc(4. * PI / 3., PI / 6., 4. * PI / 3.),
+                        .with_swing_desc(4. * PI / 3., PI / 6., 4. * PI / 3.)
+                        .with_swing_damage(20.),
                     Interactable,
                 ));
             }
@@ -1,8 +1,10 @@
+use std::time::Duration;
+
 use avian2d::prelude::*;
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashSet};
 use bevy_ecs_ldtk::prelude::*;
 
-use crate::GameLayer;
+use crate::{EffectVolume, GameLayer, PreviousPosition, StatusEffect};
 
 #[derive(Bundle, Clone, LdtkIntCell)]
 pub struct PlatformBundle {
@@ -22,3 +24,359 @@ impl Default for PlatformBundle {
         }
     }
 }
+
+/// A platform that can only be stood on from above.
+/// Holds the entities that are currently passing through it.
+#[derive(Component, Clone, Default)]
+pub struct OneWayPlatform(pub HashSet<Entity>);
+
+#[derive(Bundle, Clone, Default, LdtkIntCell)]
+pub struct OneWayPlatformBundle {
+    pub platform: PlatformBundle,
+    pub one_way_platform: OneWayPlatform,
+}
+
+/// A marker component for things characters can climb
+#[derive(Component, Clone, Default)]
+pub struct Ladder;
+
+#[derive(Bundle, Clone, LdtkIntCell)]
+pub struct LadderBundle {
+    pub ladder: Ladder,
+    pub collider: Collider,
+    pub sensor: Sensor,
+    pub collision_layer: CollisionLayers,
+}
+
+impl Default for LadderBundle {
+    fn default() -> Self {
+        Self {
+            ladder: Ladder,
+            collider: Collider::rectangle(16.0, 16.0),
+            sensor: Sensor,
+            collision_layer: CollisionLayers::new(GameLayer::CLIMBABLE, [GameLayer::CHARACTER]),
+        }
+    }
+}
+
+/// A sensor volume of water, lava or anything else characters swim through
+#[derive(Component, Clone)]
+pub struct Liquid {
+    /// Scales the buoyancy of characters swimming in it, thicker liquids float them up faster
+    pub density: f32,
+}
+
+#[derive(Bundle, Clone)]
+pub struct LiquidBundle {
+    pub liquid: Liquid,
+    pub collider: Collider,
+    pub sensor: Sensor,
+    pub collision_layer: CollisionLayers,
+}
+
+impl LiquidBundle {
+    pub fn new(density: f32) -> Self {
+        Self {
+            liquid: Liquid { density },
+            collider: Collider::rectangle(16.0, 16.0),
+            sensor: Sensor,
+            collision_layer: CollisionLayers::new(GameLayer::LIQUID, [GameLayer::CHARACTER]),
+        }
+    }
+}
+
+#[derive(Bundle, Clone, LdtkIntCell)]
+pub struct WaterBundle {
+    pub liquid: LiquidBundle,
+}
+
+impl Default for WaterBundle {
+    fn default() -> Self {
+        Self {
+            liquid: LiquidBundle::new(1.),
+        }
+    }
+}
+
+#[derive(Bundle, Clone, LdtkIntCell)]
+pub struct LavaBundle {
+    pub liquid: LiquidBundle,
+    pub effect_volume: EffectVolume,
+}
+
+impl Default for LavaBundle {
+    fn default() -> Self {
+        Self {
+            liquid: LiquidBundle::new(1.5),
+            // Burns quickly and drags characters down while they're in it
+            effect_volume: EffectVolume(vec![
+                StatusEffect::burn(4., 1000).with_tick(250),
+                StatusEffect::slow(0.5, 300),
+            ]),
+        }
+    }
+}
+
+/// The size of an LDtk grid cell
+pub const GRID_SIZE: f32 = 16.;
+
+/// A marker component for kinematic platforms that carry characters along a path
+#[derive(Component, Default)]
+pub struct MovingPlatform;
+
+/// What a moving platform does once it reaches the end of its path
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PathMode {
+    /// Turn around and go back the way it came
+    #[default]
+    PingPong,
+    /// Head straight back to the first point
+    Loop,
+}
+
+/// The points a moving platform travels between, relative to where it spawned
+#[derive(Component, Default)]
+pub struct PlatformPath {
+    pub origin: Option<Vec2>,
+    pub points: Vec<Vec2>,
+    pub mode: PathMode,
+}
+
+impl PlatformPath {
+    /// Builds a path from the `Path` and `Mode` fields of an LDtk entity.
+    /// The spawn point is always the first point.
+    pub fn from_entity_instance(entity_instance: &EntityInstance) -> Self {
+        let mut points = vec![Vec2::ZERO];
+        if let Ok(path) = entity_instance.get_maybe_points_field("Path") {
+            // LDtk grid coordinates grow downwards
+            points.extend(path.iter().flatten().map(|point| {
+                Vec2::new(
+                    (point.x - entity_instance.grid.x) as f32,
+                    (entity_instance.grid.y - point.y) as f32,
+                ) * GRID_SIZE
+            }));
+        }
+        let mode = match entity_instance.get_enum_field("Mode").map(String::as_str) {
+            Ok("Loop") => PathMode::Loop,
+            _ => PathMode::PingPong,
+        };
+        Self {
+            origin: None,
+            points,
+            mode,
+        }
+    }
+    /// The world position of a point on the path
+    pub fn point(&self, index: usize) -> Vec2 {
+        self.origin.unwrap_or_default() + self.points[index]
+    }
+}
+
+/// The speed a moving platform travels at
+#[derive(Component)]
+pub struct PlatformSpeed(pub f32);
+
+impl PlatformSpeed {
+    pub fn from_entity_instance(entity_instance: &EntityInstance) -> Self {
+        Self(*entity_instance.get_float_field("Speed").unwrap_or(&60.))
+    }
+}
+
+/// The easing curve used between two points of a path
+#[derive(Component)]
+pub struct PlatformCurve(pub CubicSegment<Vec2>);
+
+impl PlatformCurve {
+    /// Builds the curve from the `Curve` field of an LDtk entity, given as `[x1, y1, x2, y2]`
+    pub fn from_entity_instance(entity_instance: &EntityInstance) -> Self {
+        let controls = entity_instance
+            .get_maybe_floats_field("Curve")
+            .map(|curve| curve.iter().flatten().copied().collect::<Vec<f32>>())
+            .unwrap_or_default();
+        match controls[..] {
+            [x1, y1, x2, y2] => Self(CubicSegment::new_bezier(
+                Vec2::new(x1, y1),
+                Vec2::new(x2, y2),
+            )),
+            _ => Self::default(),
+        }
+    }
+}
+
+impl Default for PlatformCurve {
+    fn default() -> Self {
+        Self(CubicSegment::new_bezier(
+            Vec2::new(0.25, 0.1),
+            Vec2::new(0.25, 1.),
+        ))
+    }
+}
+
+/// Where a moving platform is along its path
+#[derive(Component)]
+pub struct PlatformProgress {
+    /// The index of the point the platform is heading from
+    pub from: usize,
+    /// Whether a ping-pong path is being walked backwards
+    pub reversed: bool,
+    pub timer: Timer,
+}
+
+impl PlatformProgress {
+    /// The index of the point the platform is heading to
+    pub fn to(&self, path: &PlatformPath) -> usize {
+        match (path.mode, self.reversed) {
+            (PathMode::Loop, _) => (self.from + 1) % path.points.len(),
+            (PathMode::PingPong, false) => self.from + 1,
+            (PathMode::PingPong, true) => self.from - 1,
+        }
+    }
+    /// Moves on to the next segment of the path and times it for the platform's speed
+    pub fn advance(&mut self, path: &PlatformPath, speed: &PlatformSpeed) {
+        self.from = self.to(path);
+        if path.mode == PathMode::PingPong {
+            if self.from == path.points.len() - 1 {
+                self.reversed = true;
+            } else if self.from == 0 {
+                self.reversed = false;
+            }
+        }
+        self.time_segment(path, speed);
+    }
+    pub fn time_segment(&mut self, path: &PlatformPath, speed: &PlatformSpeed) {
+        let length = path.point(self.from).distance(path.point(self.to(path)));
+        self.timer
+            .set_duration(Duration::from_secs_f32(length / speed.0.max(f32::EPSILON)));
+        self.timer.reset();
+    }
+}
+
+impl Default for PlatformProgress {
+    fn default() -> Self {
+        Self {
+            from: 0,
+            reversed: false,
+            timer: Timer::new(Duration::ZERO, TimerMode::Once),
+        }
+    }
+}
+
+fn entity_collider(entity_instance: &EntityInstance) -> Collider {
+    Collider::rectangle(entity_instance.width as f32, entity_instance.height as f32)
+}
+
+#[derive(Bundle, LdtkEntity)]
+pub struct MovingPlatformBundle {
+    pub moving_platform: MovingPlatform,
+    pub rigid_body: RigidBody,
+    #[with(entity_collider)]
+    pub collider: Collider,
+    pub friction: Friction,
+    pub collision_layer: CollisionLayers,
+    #[with(PlatformPath::from_entity_instance)]
+    pub path: PlatformPath,
+    #[with(PlatformSpeed::from_entity_instance)]
+    pub speed: PlatformSpeed,
+    #[with(PlatformCurve::from_entity_instance)]
+    pub curve: PlatformCurve,
+    pub progress: PlatformProgress,
+    pub previous_position: PreviousPosition,
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: LdtkSpriteSheetBundle,
+}
+
+impl Default for MovingPlatformBundle {
+    fn default() -> Self {
+        Self {
+            moving_platform: MovingPlatform,
+            rigid_body: RigidBody::Kinematic,
+            collider: Collider::rectangle(GRID_SIZE * 3., GRID_SIZE),
+            friction: Friction::new(0.5).with_static_coefficient(0.),
+            collision_layer: CollisionLayers::new(GameLayer::GROUND, [GameLayer::CHARACTER]),
+            path: PlatformPath::default(),
+            speed: PlatformSpeed(60.),
+            curve: PlatformCurve::default(),
+            progress: PlatformProgress::default(),
+            previous_position: PreviousPosition::default(),
+            sprite_sheet_bundle: LdtkSpriteSheetBundle::default(),
+        }
+    }
+}
+
+/// An area that changes which way is up, and how hard gravity pulls, for characters inside it
+#[derive(Component, Clone)]
+pub struct GravityZone {
+    pub up: Dir2,
+    pub strength: f32,
+}
+
+impl GravityZone {
+    /// Builds the zone from the `Up` and `Gravity` fields of an LDtk entity
+    pub fn from_entity_instance(entity_instance: &EntityInstance) -> Self {
+        let up = match entity_instance.get_enum_field("Up").map(String::as_str) {
+            Ok("Down") => Dir2::NEG_Y,
+            Ok("Left") => Dir2::NEG_X,
+            Ok("Right") => Dir2::X,
+            _ => Dir2::Y,
+        };
+        let strength = *entity_instance.get_float_field("Gravity").unwrap_or(&1.);
+        Self {
+            up,
+            strength: strength.max(0.),
+        }
+    }
+}
+
+impl Default for GravityZone {
+    fn default() -> Self {
+        Self {
+            up: Dir2::Y,
+            strength: 1.,
+        }
+    }
+}
+
+#[derive(Bundle, LdtkEntity)]
+pub struct GravityZoneBundle {
+    #[with(GravityZone::from_entity_instance)]
+    pub gravity_zone: GravityZone,
+    #[with(entity_collider)]
+    pub collider: Collider,
+    pub sensor: Sensor,
+    pub collision_layer: CollisionLayers,
+}
+
+impl Default for GravityZoneBundle {
+    fn default() -> Self {
+        Self {
+            gravity_zone: GravityZone::default(),
+            collider: Collider::rectangle(GRID_SIZE, GRID_SIZE),
+            sensor: Sensor,
+            collision_layer: CollisionLayers::new(GameLayer::GRAVITYZONE, [GameLayer::CHARACTER]),
+        }
+    }
+}
+
+/// Where a [`DeathPolicy::Respawn`](crate::DeathPolicy) player comes back after touching it
+#[derive(Component, Clone, Default)]
+pub struct Checkpoint;
+
+#[derive(Bundle, LdtkEntity)]
+pub struct CheckpointBundle {
+    pub checkpoint: Checkpoint,
+    #[with(entity_collider)]
+    pub collider: Collider,
+    pub sensor: Sensor,
+    pub collision_layer: CollisionLayers,
+}
+
+impl Default for CheckpointBundle {
+    fn default() -> Self {
+        Self {
+            checkpoint: Checkpoint,
+            collider: Collider::rectangle(GRID_SIZE, GRID_SIZE),
+            sensor: Sensor,
+            collision_layer: CollisionLayers::new(GameLayer::CHECKPOINT, [GameLayer::CHARACTER]),
+        }
+    }
+}
@@ -1,11 +1,15 @@
 use std::f32::consts::PI;
 
+use avian2d::{math::*, prelude::*};
 use bevy::{prelude::*, sprite::Anchor};
 use bevy_ecs_ldtk::prelude::*;
 
 use crate::{
-    GraphicsBundle, GreenLightingBundle, HahaBundle, ItemBundle, PlatformBundle, Player,
-    PlayerBundle,
+    update_grounded, ApplyStatusEffect, Checkpoint, CheckpointBundle, DropThrough, GraphicsBundle,
+    GravityZoneBundle, GreenLightingBundle, HahaBundle, ItemBundle, LadderBundle, LavaBundle,
+    MovingPlatform, MovingPlatformBundle, OneWayPlatform, OneWayPlatformBundle, PlatformBundle,
+    PlatformCurve, PlatformPath, PlatformProgress, PlatformSpeed, Player, PlayerBundle, SpawnPoint,
+    StatusEffect, UpVector, WaterBundle,
 };
 
 pub struct LevelPlugin;
@@ -18,12 +22,22 @@ impl Plugin for LevelPlugin {
             //.register_ldtk_entity::<HahaBundle>("Haha")
             //.register_default_ldtk_entity_for_layer::<ItemBundle>("Items")
             .register_ldtk_int_cell_for_layer::<PlatformBundle>("TileIntGrid", 1)
+            .register_ldtk_int_cell_for_layer::<OneWayPlatformBundle>("TileIntGrid", 2)
+            .register_ldtk_int_cell_for_layer::<LadderBundle>("TileIntGrid", 3)
+            .register_ldtk_int_cell_for_layer::<WaterBundle>("LiquidIntGrid", 1)
+            .register_ldtk_int_cell_for_layer::<LavaBundle>("LiquidIntGrid", 2)
+            .register_ldtk_entity::<MovingPlatformBundle>("MovingPlatform")
+            .register_ldtk_entity::<GravityZoneBundle>("GravityZone")
+            .register_ldtk_entity::<CheckpointBundle>("Checkpoint")
             //.register_ldtk_int_cell_for_layer::<GreenLightingBundle>("IntGridLighting", 1)
             .insert_resource(LdtkSettings {
                 level_background: LevelBackground::Nonexistent,
                 ..default()
             })
-            .add_systems(Startup, spawn_level);
+            .add_systems(Startup, spawn_level)
+            .add_systems(Update, activate_checkpoints)
+            .add_systems(FixedUpdate, move_platforms.before(update_grounded))
+            .add_systems(PostProcessCollisions, one_way_platform);
     }
 }
 
@@ -33,3 +47,133 @@ pub fn spawn_level(mut commands: Commands, asset_server: Res<AssetServer>) {
         ..Default::default()
     });
 }
+
+type OneWayCollider<'a> = (Option<&'a DropThrough>, Option<&'a UpVector>);
+
+/// Drops contacts with [`OneWayPlatform`]s unless the other collider is landing on top of it,
+/// going by its [`UpVector`] if it has one. Anything that is [`DropThrough`]ing the platform
+/// passes straight through.
+pub fn one_way_platform(
+    mut one_way_platforms_query: Query<&mut OneWayPlatform>,
+    other_colliders_query: Query<OneWayCollider, (With<Collider>, Without<OneWayPlatform>)>,
+    mut collisions: ResMut<Collisions>,
+) {
+    collisions.retain(|contacts| {
+        fn any_penetrating(contacts: &Contacts) -> bool {
+            contacts.manifolds.iter().any(|manifold| {
+                manifold
+                    .contacts
+                    .iter()
+                    .any(|contact| contact.penetration > 0.)
+            })
+        }
+
+        // The platform's own normal is the one that matters
+        let (mut one_way_platform, other_entity, platform_is_first) =
+            if let Ok(one_way_platform) = one_way_platforms_query.get_mut(contacts.entity1) {
+                (one_way_platform, contacts.entity2, true)
+            } else if let Ok(one_way_platform) = one_way_platforms_query.get_mut(contacts.entity2) {
+                (one_way_platform, contacts.entity1, false)
+            } else {
+                return true;
+            };
+
+        // Keep passing through until the other collider is clear of the platform
+        if one_way_platform.0.contains(&other_entity) {
+            if any_penetrating(contacts) {
+                return false;
+            }
+            one_way_platform.0.remove(&other_entity);
+        }
+
+        let (drop_through, up_vector) = other_colliders_query.get(other_entity).unwrap_or_default();
+        if let Some(drop_through) = drop_through {
+            if drop_through.is_dropping_through(contacts.entity1)
+                || drop_through.is_dropping_through(contacts.entity2)
+            {
+                one_way_platform.0.insert(other_entity);
+                return false;
+            }
+        }
+
+        let up = up_vector.map_or(Vector::Y, |up_vector| up_vector.0.as_vec2());
+        let is_landing_on_top = contacts.manifolds.iter().all(|manifold| {
+            let normal = if platform_is_first {
+                manifold.normal1
+            } else {
+                manifold.normal2
+            };
+            normal.length() > Scalar::EPSILON && normal.dot(up) >= 0.5
+        });
+        if is_landing_on_top {
+            true
+        } else if any_penetrating(contacts) {
+            one_way_platform.0.insert(other_entity);
+            false
+        } else {
+            true
+        }
+    });
+}
+
+type PlatformMotion<'a> = (
+    &'a Position,
+    &'a mut LinearVelocity,
+    &'a mut PlatformPath,
+    &'a PlatformSpeed,
+    &'a PlatformCurve,
+    &'a mut PlatformProgress,
+);
+
+/// Drives [`MovingPlatform`]s along their [`PlatformPath`] by setting their velocity.
+pub fn move_platforms(mut query: Query<PlatformMotion, With<MovingPlatform>>, time: Res<Time>) {
+    let delta_seconds = time.delta_seconds().adjust_precision();
+    if delta_seconds == 0. {
+        return;
+    }
+    for (position, mut linear_velocity, mut path, speed, curve, mut progress) in query.iter_mut() {
+        if path.points.len() < 2 {
+            continue;
+        }
+        if path.origin.is_none() {
+            path.origin = Some(position.0);
+            progress.time_segment(&path, speed);
+        }
+
+        progress.timer.tick(time.delta());
+        if progress.timer.finished() {
+            progress.advance(&path, speed);
+        }
+        let from = path.point(progress.from);
+        let to = path.point(progress.to(&path));
+        let target = from.lerp(to, curve.0.ease(progress.timer.fraction()));
+        // Kinematic bodies need a velocity rather than a teleport to push what's on them
+        linear_velocity.0 = (target - position.0) / delta_seconds;
+    }
+}
+
+/// Moves the [`SpawnPoint`] of players to the centre of the last [`Checkpoint`] they touched,
+/// patching them up with a short regeneration as it does.
+pub fn activate_checkpoints(
+    checkpoints: Query<&GlobalTransform, With<Checkpoint>>,
+    mut query: Query<(Entity, &CollidingEntities, &mut SpawnPoint), With<Player>>,
+    mut apply_status_effect_event_writer: EventWriter<ApplyStatusEffect>,
+) {
+    for (entity, colliding_entities, mut spawn_point) in query.iter_mut() {
+        let Some(checkpoint) = colliding_entities
+            .iter()
+            .find_map(|colliding_entity| checkpoints.get(*colliding_entity).ok())
+        else {
+            continue;
+        };
+        let checkpoint_position = checkpoint.translation().truncate();
+        if spawn_point.0 != Some(checkpoint_position) {
+            spawn_point.0 = Some(checkpoint_position);
+            apply_status_effect_event_writer.send(ApplyStatusEffect {
+                target: entity,
+                source: None,
+                effect: StatusEffect::regen(5., 3000),
+            });
+        }
+    }
+}
@@ -36,15 +36,19 @@ import_game_modules!(
     items,
     level,
     movement,
-    player
+    player,
+    status_effects
 );
 
+/// How many times a second the controller and physics run
+const FIXED_HZ: f64 = 64.;
+
 fn main() {
     App::new()
         .add_plugins((
             DefaultPlugins.set(ImagePlugin::default_nearest()),
             EditorPlugin::default(),
-            PhysicsPlugins::default(),
+            PhysicsPlugins::new(FixedPostUpdate),
             CharacterControllerPlugin,
             InputControllerPlugin,
             InteractablePlugin,
@@ -55,8 +59,12 @@ fn main() {
             HealthPlugin,
             EnemyPlugin,
             PlayerPlugin,
+            StatusEffectPlugin,
             LevelPlugin, //WorldInspectorPlugin::new()
         ))
+        // Physics steps once per fixed update, at the same rate as the controller
+        .insert_resource(Time::<Fixed>::from_hz(FIXED_HZ))
+        .insert_resource(Time::new_with(Physics::fixed_once_hz(FIXED_HZ)))
         .insert_resource(ClearColor(Color::linear_rgb(0.3, 0.2, 0.0)))
         .run();
 }
@@ -1,9 +1,18 @@
 use std::time::Duration;
 
 use avian2d::{math::*, prelude::*};
-use bevy::prelude::*;
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::HashSet,
+};
+use bevy_ecs_ldtk::prelude::*;
 use bevy_inspector_egui::prelude::*;
 use bevy_reflect::Reflect;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::Facing;
 
 /// Collision Layers
 #[derive(PhysicsLayer)]
@@ -11,6 +20,10 @@ pub enum GameLayer {
     NOTHING,
     CHARACTER,
     GROUND,
+    CLIMBABLE,
+    LIQUID,
+    GRAVITYZONE,
+    CHECKPOINT,
 }
 /// An event sent for a movement input action.
 #[derive(Event)]
@@ -25,6 +38,27 @@ impl MovementEvent {
     }
 }
 
+/// An event sent to knock a character back, taking away control for the hitstun.
+#[derive(Event)]
+pub struct KnockbackEvent {
+    pub entity: Entity,
+    pub direction: Dir2,
+    pub speed: Scalar,
+    /// In milliseconds
+    pub hitstun: u64,
+}
+
+impl KnockbackEvent {
+    pub fn new(entity: Entity, direction: Dir2, speed: Scalar, hitstun: u64) -> Self {
+        Self {
+            entity,
+            direction,
+            speed,
+            hitstun,
+        }
+    }
+}
+
 pub enum MovementAction {
     RunRight,
     RunLeft,
@@ -32,6 +66,12 @@ pub enum MovementAction {
     JumpStart,
     JumpEnd,
     Fall,
+    Dash,
+    ClimbUp,
+    ClimbDown,
+    ClimbEnd,
+    Crouch,
+    CrouchEnd,
 }
 
 /// A marker component indicating that an entity is using a character controller.
@@ -43,14 +83,146 @@ pub struct CharacterController;
 #[component(storage = "SparseSet")]
 pub struct Grounded;
 
+/// Lets a character fall through the one-way platforms it was standing on
+#[derive(Component)]
+pub struct DropThrough {
+    pub platforms: HashSet<Entity>,
+    pub timer: Timer,
+}
+
+impl DropThrough {
+    pub fn new(platforms: HashSet<Entity>, duration: u64) -> Self {
+        Self {
+            platforms,
+            timer: Timer::new(Duration::from_millis(duration), TimerMode::Once),
+        }
+    }
+    pub fn is_dropping_through(&self, platform: Entity) -> bool {
+        !self.timer.finished() && self.platforms.contains(&platform)
+    }
+}
+
+/// The gap under a character that still counts as touching the ground, the ground caster
+/// is slightly smaller than the collider so there's always a little
+pub const GROUND_SKIN: Scalar = 0.5;
+
+/// Which way is up for a character. The controller rotates to match it and
+/// does all its movement relative to it, so running right on a ceiling heads left on screen.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+pub struct UpVector(pub Dir2);
+
+impl UpVector {
+    /// The direction a character runs when going right
+    pub fn right(&self) -> Vector {
+        Vector::new(self.0.y, -self.0.x)
+    }
+    /// Turns a world space vector into one relative to the character, where +Y is up
+    pub fn to_local(self, vector: Vector) -> Vector {
+        Vector::new(vector.dot(self.right()), vector.dot(*self.0))
+    }
+    /// Turns a vector relative to the character back into world space
+    pub fn to_world(self, vector: Vector) -> Vector {
+        self.right() * vector.x + *self.0 * vector.y
+    }
+    /// The rotation that stands a character upright
+    pub fn rotation(&self) -> Rotation {
+        Rotation::radians(self.0.to_angle() - PI / 2.)
+    }
+}
+
+impl Default for UpVector {
+    fn default() -> Self {
+        Self(Dir2::Y)
+    }
+}
+
+/// How strongly a character is pulled down, 1 is normal gravity
+#[derive(Component, Reflect)]
+pub struct GravityStrength(pub Scalar);
+
+impl Default for GravityStrength {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+/// The closest surface under a character
+#[derive(Clone, Copy, Debug)]
+pub struct GroundSurface {
+    pub normal: Vector,
+    pub distance: Scalar,
+    /// Whether the surface is steeper than the character's [`MaxSlopeAngle`]
+    pub is_steep: bool,
+}
+
+impl GroundSurface {
+    /// The direction along the surface, pointing right
+    pub fn tangent(&self) -> Vector {
+        Vector::new(self.normal.y, -self.normal.x)
+    }
+    /// The direction along the surface, pointing down
+    pub fn downhill(&self) -> Vector {
+        let tangent = self.tangent();
+        if tangent.y > 0. {
+            -tangent
+        } else {
+            tangent
+        }
+    }
+}
+
+/// The ground a character's ground caster last saw
+#[derive(Component, Default)]
+pub struct GroundContact(pub Option<GroundSurface>);
+
+/// How far a character is pulled down to stay on a downhill slope
+#[derive(Component, Reflect)]
+pub struct GroundSnapDistance(pub Scalar);
+
 /// The time a character hangs (aka Cyote Time)
 #[derive(Component, Reflect)]
 pub struct HangTime(pub Timer);
 
+/// The time an early jump press is remembered for before landing (aka Jump Buffering)
+#[derive(Component, Reflect)]
+pub struct JumpBuffer {
+    pub timer: Timer,
+    /// Whether jump was let go before the buffered jump fired, so it starts out released
+    pub released: bool,
+}
+
+impl JumpBuffer {
+    pub fn new(timer: Timer) -> Self {
+        Self {
+            timer,
+            released: false,
+        }
+    }
+    pub fn is_buffered(&self) -> bool {
+        !self.timer.paused() && !self.timer.finished()
+    }
+    /// Remembers a jump press
+    pub fn buffer(&mut self) {
+        self.timer.reset();
+        self.timer.unpause();
+        self.released = false;
+    }
+    /// Forgets the buffered press
+    pub fn clear(&mut self) {
+        self.timer.pause();
+        self.timer.reset();
+        self.released = false;
+    }
+}
+
 /// The acceleration used for character movement.
 #[derive(Component, Reflect)]
 pub struct MovementAcceleration(pub Scalar);
 
+/// The rate damping factors are given at, a factor of 0.9 keeps 90% of the velocity
+/// every 60th of a second
+pub const DAMPING_REFERENCE_RATE: Scalar = 60.;
+
 /// The damping factor used for slowing down movement.
 #[derive(Component, Reflect)]
 pub struct GroundMovementDampingFactor(pub Scalar);
@@ -59,25 +231,74 @@ pub struct GroundMovementDampingFactor(pub Scalar);
 #[derive(Component, Reflect)]
 pub struct AirMovementDampingFactor(pub Scalar);
 
+/// The velocity of the moving platform a character is standing on
+#[derive(Component, Default)]
+pub struct PlatformVelocity(pub Vector);
+
+/// The physics position from the previous fixed step, used to interpolate the transform
+#[derive(Component, Default)]
+pub struct PreviousPosition(pub Option<Vector>);
+
 /// The strength of a jump.
 #[derive(Component, Reflect)]
 pub struct JumpImpulse(pub Scalar);
 
-/// The acceleration curve of a jump
-#[derive(Component, Reflect)]
-pub struct JumpCurve(pub CubicSegment<Vec2>);
-
-/// The length of a jump
+/// The time since a jump started, its duration is the time it takes to reach the apex
 #[derive(Component, Reflect)]
 pub struct JumpTimer(pub Timer);
 
-/// The hieght of a jump
+/// The hieght of a jump's apex
 #[derive(Component, Reflect)]
 pub struct JumpHeight(pub Scalar);
 
+impl JumpHeight {
+    /// The gravity that stops a jump at this height after `time_to_apex` seconds.
+    /// The [`ApexHang`] stretches the rise a little past `time_to_apex`
+    pub fn gravity(&self, time_to_apex: Scalar) -> Scalar {
+        2. * self.0 / time_to_apex.powi(2)
+    }
+}
+
+/// Scales the height of jumps without changing the gravity that brings them back down
+#[derive(Component, Reflect)]
+pub struct JumpHeightMultiplier(pub Scalar);
+
+/// The gravity multiplier used while still rising after jump is let go
+#[derive(Component, Reflect)]
+pub struct JumpReleaseGravity(pub Scalar);
+
+/// Whether jump was let go before reaching the apex
+#[derive(Component, Reflect)]
+pub struct JumpReleased(pub bool);
+
+/// The band around a jump's apex with reduced gravity, for a little hang time at the top
+#[derive(Component, Reflect)]
+pub struct ApexHang {
+    /// Vertical speeds below this are inside the band
+    pub speed: Scalar,
+    pub gravity_multiplier: Scalar,
+}
+
+impl ApexHang {
+    /// The launch velocity that peaks at `height` under `gravity`, taking the band's lower
+    /// gravity into account so the hang time doesn't carry the jump any higher
+    pub fn launch_velocity(&self, height: Scalar, gravity: Scalar) -> Scalar {
+        let band_gravity = gravity * self.gravity_multiplier.max(Scalar::EPSILON);
+        // How high the band alone carries a jump entering it at full speed
+        let band_height = self.speed.powi(2) / (2. * band_gravity);
+        if height <= band_height {
+            (2. * band_gravity * height).sqrt()
+        } else {
+            (2. * gravity * (height - band_height) + self.speed.powi(2)).sqrt()
+        }
+    }
+}
+
 #[derive(Component, PartialEq, Debug)]
 pub enum JumpFallState {
     Jumping,
+    /// Kicking off a wall, the value is the horizontal direction away from it
+    WallJumping(f32),
     Falling,
     Idle,
 }
@@ -85,10 +306,125 @@ pub enum JumpFallState {
 #[derive(Component, PartialEq, Debug)]
 pub enum MoveState {
     Running(f32),
-    Dashing,
+    /// Dashing in `direction`, `resume` is the run direction to go back to once the dash ends
+    Dashing {
+        direction: f32,
+        resume: Option<f32>,
+    },
     Idle,
+    /// Climbing a ladder, the value is the vertical direction (0 to hold still)
+    Climbing(f32),
+    /// Sliding along the ground in `direction` after crouching out of a run
+    Sliding {
+        direction: f32,
+        speed: f32,
+    },
+}
+
+/// A marker component indicating that an entity is overlapping something climbable
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct OnLadder;
+
+/// A marker component for characters inside a liquid volume, holding the liquid's density
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Submerged(pub f32);
+
+/// The horizontal speed a character swims at
+#[derive(Component, Reflect)]
+pub struct SwimSpeed(pub f32);
+
+/// The upward kick of a swim stroke, and the time before the next one
+#[derive(Component, Reflect)]
+pub struct SwimStroke {
+    pub speed: f32,
+    pub cooldown: Timer,
+}
+
+/// The pull of a liquid on a character.
+/// Sinking and floating fight each other, the liquid's density scales the float.
+#[derive(Component, Reflect)]
+pub struct Buoyancy {
+    pub sink: Scalar,
+    pub float: Scalar,
+    /// The fastest a character can sink or rise without stroking
+    pub max_speed: Scalar,
+}
+
+/// The damping factor used for slowing down movement in liquids, on both axes.
+#[derive(Component, Reflect)]
+pub struct SwimDampingFactor(pub Scalar);
+
+/// A bundle containing all nessacary Swim Components
+#[derive(Bundle)]
+pub struct SwimBundle {
+    pub swim_speed: SwimSpeed,
+    pub swim_stroke: SwimStroke,
+    pub buoyancy: Buoyancy,
+    pub swim_damping: SwimDampingFactor,
 }
 
+impl SwimBundle {
+    pub fn new(
+        speed: f32,
+        stroke_speed: f32,
+        stroke_cooldown: u64,
+        sink: Scalar,
+        float: Scalar,
+        max_speed: Scalar,
+        damping: Scalar,
+    ) -> Self {
+        // The first stroke shouldn't have to wait for the cooldown
+        let mut cooldown = Timer::new(Duration::from_millis(stroke_cooldown), TimerMode::Once);
+        cooldown.tick(Duration::from_millis(stroke_cooldown));
+        Self {
+            swim_speed: SwimSpeed(speed),
+            swim_stroke: SwimStroke {
+                speed: stroke_speed,
+                cooldown,
+            },
+            buoyancy: Buoyancy {
+                sink,
+                float,
+                max_speed,
+            },
+            swim_damping: SwimDampingFactor(damping),
+        }
+    }
+}
+
+impl Default for SwimBundle {
+    fn default() -> Self {
+        Self::new(120., 220., 250, 300., 340., 120., 0.95)
+    }
+}
+
+/// A character that has been knocked back and ignores [`MovementEvent`]s until the timer finishes
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Hitstun {
+    pub velocity: Vector,
+    pub timer: Timer,
+}
+
+/// How knockback slows down over the hitstun
+#[derive(Component, Reflect)]
+pub struct KnockbackCurve(pub CubicSegment<Vec2>);
+
+impl Default for KnockbackCurve {
+    fn default() -> Self {
+        Self(CubicSegment::new_bezier(
+            Vec2::new(0.25, 0.1),
+            Vec2::new(0.25, 1.),
+        ))
+    }
+}
+
+/// The speed a character climbs at
+#[derive(Component, Reflect)]
+pub struct ClimbSpeed(pub f32);
+
 /// The initial move velocity
 #[derive(Component, Reflect)]
 pub struct InitialRunSpeed(pub f32);
@@ -139,8 +475,11 @@ impl Default for RunBundle {
 #[derive(Bundle)]
 pub struct JumpBundle {
     pub jump_height: JumpHeight,
+    pub jump_height_multiplier: JumpHeightMultiplier,
     pub jump_timer: JumpTimer,
-    pub jump_curve: JumpCurve,
+    pub jump_release_gravity: JumpReleaseGravity,
+    pub jump_released: JumpReleased,
+    pub apex_hang: ApexHang,
     pub max_jump_count: MaxJumpCount,
     pub jump_fall_counter: JumpFallCounter,
     pub jump_fall_state: JumpFallState,
@@ -149,15 +488,25 @@ pub struct JumpBundle {
 impl JumpBundle {
     pub fn new(
         height: f32,
-        duration: u64,
+        time_to_apex: u64,
         max_jump_count: i32,
-        curve_control1: Vec2,
-        curve_control2: Vec2,
+        release_gravity_multiplier: f32,
+        apex_hang_speed: f32,
+        apex_gravity_multiplier: f32,
     ) -> Self {
         Self {
             jump_height: JumpHeight(height),
-            jump_timer: JumpTimer(Timer::new(Duration::from_millis(duration), TimerMode::Once)),
-            jump_curve: JumpCurve(CubicSegment::new_bezier(curve_control1, curve_control2)),
+            jump_height_multiplier: JumpHeightMultiplier(1.),
+            jump_timer: JumpTimer(Timer::new(
+                Duration::from_millis(time_to_apex),
+                TimerMode::Once,
+            )),
+            jump_release_gravity: JumpReleaseGravity(release_gravity_multiplier),
+            jump_released: JumpReleased(false),
+            apex_hang: ApexHang {
+                speed: apex_hang_speed,
+                gravity_multiplier: apex_gravity_multiplier,
+            },
             max_jump_count: MaxJumpCount(max_jump_count),
             jump_fall_counter: JumpFallCounter(0),
             jump_fall_state: JumpFallState::Falling,
@@ -167,15 +516,7 @@ impl JumpBundle {
 
 impl Default for JumpBundle {
     fn default() -> Self {
-        Self::new(100., 250, 1, Vec2::new(0.25, 0.1), Vec2::new(0.25, 1.))
-        //Self {
-        //    jump_height: JumpHeight(100.),
-        //    jump_timer: JumpTimer(Timer::new(Duration::from_millis(250), TimerMode::Once)),
-        //    jump_curve: JumpCurve(CubicSegment::new_bezier(Vec2::new(0.25,0.1), Vec2::new(0.25, 1.))),
-        //    max_jump_count: MaxJumpCount(1),
-        //    jump_fall_counter: JumpFallCounter(0),
-        //    jump_fall_state: JumpFallState::Falling
-        //}
+        Self::new(100., 250, 1, 3., 40., 0.5)
     }
 }
 
@@ -223,6 +564,264 @@ impl Default for FallBundle {
     }
 }
 
+/// The shapes a character collides and casts with
+pub struct ControllerShape {
+    pub collider: Collider,
+    pub ground_caster: Collider,
+    pub wall_caster: Collider,
+}
+
+impl ControllerShape {
+    pub fn new(collider: Collider) -> Self {
+        // Create shape caster as a slightly smaller version of collider
+        let mut ground_caster = collider.clone();
+        ground_caster.set_scale(Vector::ONE * 0.99, 10);
+        Self {
+            wall_caster: WallCaster::new(&collider, 2.).shape,
+            ground_caster,
+            collider,
+        }
+    }
+}
+
+/// The standing and crouching shapes of a character, along with the space
+/// that has to be free to stand back up
+#[derive(Component)]
+pub struct CrouchShapes {
+    pub standing: ControllerShape,
+    pub crouching: ControllerShape,
+    pub headroom: Collider,
+}
+
+impl CrouchShapes {
+    /// Shrinks `collider` to `crouch_height` (a fraction of its height), keeping the feet in place
+    pub fn new(collider: &Collider, crouch_height: Scalar) -> Self {
+        fn shifted(collider: &Collider, scale: Vector, offset: Scalar) -> Collider {
+            let mut scaled = collider.clone();
+            scaled.set_scale(scale, 10);
+            Collider::compound(vec![(
+                Position::from_xy(0., offset),
+                Rotation::default(),
+                scaled,
+            )])
+        }
+
+        let aabb = collider.aabb(Vector::ZERO, Rotation::default());
+        let half_height = (aabb.max.y - aabb.min.y) / 2.;
+        let crouch_height = crouch_height.clamp(0.1, 1.);
+        Self {
+            standing: ControllerShape::new(collider.clone()),
+            crouching: ControllerShape::new(shifted(
+                collider,
+                Vector::new(1., crouch_height),
+                -half_height * (1. - crouch_height),
+            )),
+            // Only the part above the crouched shape, so the floor never gets in the way
+            headroom: shifted(
+                collider,
+                Vector::new(0.99, 1. - crouch_height),
+                half_height * crouch_height,
+            ),
+        }
+    }
+}
+
+/// A character that is crouching.
+/// Letting go of crouch only stands up once there's headroom.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Crouching {
+    pub wants_to_stand: bool,
+}
+
+/// The fastest a character can move while crouching
+#[derive(Component, Reflect)]
+pub struct CrouchSpeed(pub f32);
+/// The run speed needed to slide instead of crouching in place
+#[derive(Component, Reflect)]
+pub struct MinSlideSpeed(pub f32);
+/// The length of a slide
+#[derive(Component, Reflect)]
+pub struct SlideTimer(pub Timer);
+/// The slide deceleration curve
+#[derive(Component, Reflect)]
+pub struct SlideCurve(pub CubicSegment<Vec2>);
+/// A bundle containing all nessacary Crouch Components
+#[derive(Bundle)]
+pub struct CrouchBundle {
+    pub crouch_speed: CrouchSpeed,
+    pub min_slide_speed: MinSlideSpeed,
+    pub slide_timer: SlideTimer,
+    pub slide_curve: SlideCurve,
+}
+
+impl CrouchBundle {
+    pub fn new(
+        crouch_speed: f32,
+        min_slide_speed: f32,
+        slide_duration: u64,
+        curve_control1: Vec2,
+        curve_control2: Vec2,
+    ) -> Self {
+        Self {
+            crouch_speed: CrouchSpeed(crouch_speed),
+            min_slide_speed: MinSlideSpeed(min_slide_speed),
+            slide_timer: SlideTimer(Timer::new(
+                Duration::from_millis(slide_duration),
+                TimerMode::Once,
+            )),
+            slide_curve: SlideCurve(CubicSegment::new_bezier(curve_control1, curve_control2)),
+        }
+    }
+}
+
+impl Default for CrouchBundle {
+    fn default() -> Self {
+        Self::new(60., 200., 450, Vec2::new(0., 0.5), Vec2::new(0.5, 1.))
+    }
+}
+
+/// The top dash velocity
+#[derive(Component, Reflect)]
+pub struct DashSpeed(pub f32);
+/// The length of a dash
+#[derive(Component, Reflect)]
+pub struct DashTimer(pub Timer);
+/// The time before another dash can start
+#[derive(Component, Reflect)]
+pub struct DashCooldown(pub Timer);
+/// The dash deceleration curve
+#[derive(Component, Reflect)]
+pub struct DashCurve(pub CubicSegment<Vec2>);
+/// The number of dashes allowed before touching the ground again
+#[derive(Component, Reflect)]
+pub struct MaxAirDashCount(pub i32);
+#[derive(Component, Reflect)]
+pub struct AirDashCounter(pub i32);
+/// A bundle containing all nessacary Dash Components
+#[derive(Bundle)]
+pub struct DashBundle {
+    pub dash_speed: DashSpeed,
+    pub dash_timer: DashTimer,
+    pub dash_cooldown: DashCooldown,
+    pub dash_curve: DashCurve,
+    pub max_air_dash_count: MaxAirDashCount,
+    pub air_dash_counter: AirDashCounter,
+}
+
+impl DashBundle {
+    pub fn new(
+        speed: f32,
+        duration: u64,
+        cooldown: u64,
+        max_air_dash_count: i32,
+        curve_control1: Vec2,
+        curve_control2: Vec2,
+    ) -> Self {
+        // The first dash shouldn't have to wait for the cooldown
+        let mut dash_cooldown = Timer::new(Duration::from_millis(cooldown), TimerMode::Once);
+        dash_cooldown.tick(Duration::from_millis(cooldown));
+        Self {
+            dash_speed: DashSpeed(speed),
+            dash_timer: DashTimer(Timer::new(Duration::from_millis(duration), TimerMode::Once)),
+            dash_cooldown: DashCooldown(dash_cooldown),
+            dash_curve: DashCurve(CubicSegment::new_bezier(curve_control1, curve_control2)),
+            max_air_dash_count: MaxAirDashCount(max_air_dash_count),
+            air_dash_counter: AirDashCounter(0),
+        }
+    }
+}
+
+impl Default for DashBundle {
+    fn default() -> Self {
+        Self::new(600., 150, 500, 1, Vec2::new(0., 1.), Vec2::new(0.5, 1.))
+    }
+}
+
+/// A marker component indicating that an entity is touching a wall on the side it holds
+#[derive(Component, Debug)]
+#[component(storage = "SparseSet")]
+pub struct TouchingWall(pub Facing);
+
+/// A side-facing cast used to look for walls next to a character
+#[derive(Component)]
+pub struct WallCaster {
+    pub shape: Collider,
+    pub max_time_of_impact: Scalar,
+}
+
+impl WallCaster {
+    pub fn new(collider: &Collider, max_time_of_impact: Scalar) -> Self {
+        // Shrink the height so the floor and ceiling aren't counted as walls
+        let mut shape = collider.clone();
+        shape.set_scale(Vector::new(0.99, 0.8), 10);
+        Self {
+            shape,
+            max_time_of_impact,
+        }
+    }
+}
+
+/// The maximum fall velocity while sliding down a wall
+#[derive(Component, Reflect)]
+pub struct MaxWallSlideSpeed(pub f32);
+
+/// The velocity a wall jump kicks off with (x is away from the wall)
+#[derive(Component, Reflect)]
+pub struct WallJumpSpeed(pub Vec2);
+
+/// The length of a wall jump
+#[derive(Component, Reflect)]
+pub struct WallJumpTimer(pub Timer);
+
+/// The wall jump deceleration curve
+#[derive(Component, Reflect)]
+pub struct WallJumpCurve(pub CubicSegment<Vec2>);
+
+/// A bundle containing all nessacary Wall Slide and Wall Jump Components
+#[derive(Bundle)]
+pub struct WallBundle {
+    pub max_wall_slide_speed: MaxWallSlideSpeed,
+    pub wall_jump_speed: WallJumpSpeed,
+    pub wall_jump_timer: WallJumpTimer,
+    pub wall_jump_curve: WallJumpCurve,
+}
+
+impl WallBundle {
+    pub fn new(
+        max_wall_slide_speed: f32,
+        wall_jump_speed: Vec2,
+        duration: u64,
+        curve_control1: Vec2,
+        curve_control2: Vec2,
+    ) -> Self {
+        Self {
+            max_wall_slide_speed: MaxWallSlideSpeed(max_wall_slide_speed),
+            wall_jump_speed: WallJumpSpeed(wall_jump_speed),
+            wall_jump_timer: WallJumpTimer(Timer::new(
+                Duration::from_millis(duration),
+                TimerMode::Once,
+            )),
+            wall_jump_curve: WallJumpCurve(CubicSegment::new_bezier(
+                curve_control1,
+                curve_control2,
+            )),
+        }
+    }
+}
+
+impl Default for WallBundle {
+    fn default() -> Self {
+        Self::new(
+            -60.,
+            Vec2::new(250., 500.),
+            200,
+            Vec2::new(0., 1.),
+            Vec2::new(0.7, 0.9),
+        )
+    }
+}
+
 /// The strength of a jump.
 #[derive(Component, Reflect)]
 pub struct FallGravityScale(pub Scalar);
@@ -248,8 +847,14 @@ pub struct CharacterControllerBundle {
     pub rigid_body: RigidBody,
     pub collider: Collider,
     pub ground_caster: ShapeCaster,
+    pub wall_caster: WallCaster,
+    pub crouch_shapes: CrouchShapes,
     pub locked_axes: LockedAxes,
     pub collision_layer: CollisionLayers,
+    pub previous_position: PreviousPosition,
+    pub platform_velocity: PlatformVelocity,
+    pub up_vector: UpVector,
+    pub gravity_strength: GravityStrength,
     //pub jump_fall_counter: JumpFallCounter,
     pub movement: MovementBundle,
 }
@@ -263,10 +868,19 @@ pub struct MovementBundle {
     pub run_bundle: RunBundle,
     pub jump_bundle: JumpBundle,
     pub fall_bundle: FallBundle,
+    pub wall_bundle: WallBundle,
+    pub dash_bundle: DashBundle,
+    pub climb_speed: ClimbSpeed,
+    pub swim_bundle: SwimBundle,
+    pub crouch_bundle: CrouchBundle,
     pub max_slope_angle: MaxSlopeAngle,
+    pub ground_contact: GroundContact,
+    pub ground_snap_distance: GroundSnapDistance,
     //pub fall_gravity_scale: FallGravityScale,
     pub gravity_scale: GravityScale,
     pub hang_timer: HangTime,
+    pub jump_buffer: JumpBuffer,
+    pub knockback_curve: KnockbackCurve,
     //pub max_jump_count: MaxJumpCount
 }
 
@@ -281,12 +895,16 @@ impl MovementBundle {
         max_slope_angle: Scalar,
         //fall_gravity_scale: Scalar,
         hang_duration: u64,
+        jump_buffer_duration: u64,
         //max_jump_count: i32
     ) -> Self {
         let mut hang_timer = Timer::default();
         hang_timer.set_mode(TimerMode::Once);
         hang_timer.set_duration(Duration::from_millis(hang_duration));
         hang_timer.pause();
+        let mut jump_buffer =
+            Timer::new(Duration::from_millis(jump_buffer_duration), TimerMode::Once);
+        jump_buffer.pause();
         Self {
             ground_damping: GroundMovementDampingFactor(ground_damping),
             air_damping: AirMovementDampingFactor(air_damping),
@@ -294,10 +912,19 @@ impl MovementBundle {
             run_bundle,
             jump_bundle,
             fall_bundle,
+            wall_bundle: WallBundle::default(),
+            dash_bundle: DashBundle::default(),
+            climb_speed: ClimbSpeed(100.),
+            swim_bundle: SwimBundle::default(),
+            crouch_bundle: CrouchBundle::default(),
             max_slope_angle: MaxSlopeAngle(max_slope_angle),
+            ground_contact: GroundContact::default(),
+            ground_snap_distance: GroundSnapDistance(4.),
             //fall_gravity_scale: FallGravityScale(fall_gravity_scale),
             gravity_scale: GravityScale(0.),
             hang_timer: HangTime(hang_timer),
+            jump_buffer: JumpBuffer::new(jump_buffer),
+            knockback_curve: KnockbackCurve::default(),
             //max_jump_count: MaxJumpCount(max_jump_count)
         }
     }
@@ -313,51 +940,241 @@ impl Default for MovementBundle {
             FallBundle::default(),
             PI * 0.45,
             100,
+            100,
         )
     }
 }
 
 impl CharacterControllerBundle {
     pub fn new(collider: Collider) -> Self {
-        // Create shape caster as a slightly smaller version of collider
-        let mut caster_shape = collider.clone();
-        caster_shape.set_scale(Vector::ONE * 0.99, 10);
+        let crouch_shapes = CrouchShapes::new(&collider, 0.5);
+        let wall_caster = WallCaster::new(&collider, 2.);
 
         Self {
             character_controller: CharacterController,
             rigid_body: RigidBody::Dynamic,
             collider,
-            ground_caster: ShapeCaster::new(caster_shape, Vector::ZERO, 0.0, Dir2::NEG_Y)
-                .with_max_time_of_impact(20.)
-                //TODO: Find a better max hit number, may be a problem with more rigid bodies
-                .with_max_hits(30),
+            ground_caster: ShapeCaster::new(
+                crouch_shapes.standing.ground_caster.clone(),
+                Vector::ZERO,
+                0.0,
+                Dir2::NEG_Y,
+            )
+            .with_max_time_of_impact(20.)
+            //TODO: Find a better max hit number, may be a problem with more rigid bodies
+            .with_max_hits(30),
+            wall_caster,
+            crouch_shapes,
             locked_axes: LockedAxes::ROTATION_LOCKED,
-            collision_layer: CollisionLayers::new(GameLayer::CHARACTER, [GameLayer::GROUND]),
+            collision_layer: CollisionLayers::new(
+                GameLayer::CHARACTER,
+                [
+                    GameLayer::GROUND,
+                    GameLayer::CLIMBABLE,
+                    GameLayer::LIQUID,
+                    GameLayer::GRAVITYZONE,
+                    GameLayer::CHECKPOINT,
+                ],
+            ),
+            previous_position: PreviousPosition::default(),
+            platform_velocity: PlatformVelocity::default(),
+            up_vector: UpVector::default(),
+            gravity_strength: GravityStrength::default(),
             movement: MovementBundle::default(),
         }
     }
+}
 
-    pub fn with_movement(
-        mut self,
-        ground_damping: Scalar,
-        air_damping: Scalar,
-        run_bundle: RunBundle,
-        jump_bundle: JumpBundle,
-        fall_bundle: FallBundle,
-        max_slope_angle: Scalar,
-        hang_duration: u64,
-    ) -> Self {
-        self.movement = MovementBundle::new(
-            ground_damping,
-            air_damping,
-            run_bundle,
-            jump_bundle,
-            fall_bundle,
-            max_slope_angle,
-            hang_duration,
-        );
-        self
+/// A cubic bezier given by its two control points, as stored in asset files
+pub type CurveControls = [[f32; 2]; 2];
+
+fn bezier(curve: &CurveControls) -> CubicSegment<Vec2> {
+    CubicSegment::new_bezier(Vec2::from_array(curve[0]), Vec2::from_array(curve[1]))
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RunProfile {
+    pub initial_speed: f32,
+    pub max_speed: f32,
+    pub duration: u64,
+    pub curve: CurveControls,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct JumpProfile {
+    pub height: f32,
+    pub time_to_apex: u64,
+    pub max_jump_count: i32,
+    pub release_gravity_multiplier: f32,
+    pub apex_hang_speed: f32,
+    pub apex_gravity_multiplier: f32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SwimProfile {
+    pub speed: f32,
+    pub stroke_speed: f32,
+    pub stroke_cooldown: u64,
+    pub sink: Scalar,
+    pub float: Scalar,
+    pub max_speed: Scalar,
+    pub damping: Scalar,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CrouchProfile {
+    /// The fraction of the collider's height kept while crouching
+    pub height: Scalar,
+    pub speed: f32,
+    pub min_slide_speed: f32,
+    pub slide_duration: u64,
+    pub slide_curve: CurveControls,
+}
+
+impl CrouchProfile {
+    pub fn slide_curve(&self) -> SlideCurve {
+        SlideCurve(bezier(&self.slide_curve))
     }
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct FallProfile {
+    pub initial_speed: f32,
+    pub max_speed: f32,
+    pub duration: u64,
+    pub curve: CurveControls,
+}
+
+/// Movement parameters for a character controller, loaded from `*.movement.ron` files
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct MovementProfile {
+    pub ground_damping: Scalar,
+    pub air_damping: Scalar,
+    /// In degrees
+    pub max_slope_angle: Scalar,
+    pub hang_duration: u64,
+    pub jump_buffer_duration: u64,
+    pub run: RunProfile,
+    pub jump: JumpProfile,
+    pub fall: FallProfile,
+    /// Keeps the controller's swimming as it is when left out
+    #[serde(default)]
+    pub swim: Option<SwimProfile>,
+    /// Keeps the controller's crouching as it is when left out
+    #[serde(default)]
+    pub crouch: Option<CrouchProfile>,
+}
+
+impl MovementProfile {
+    /// Rejects values that would stall timers, flip gravity or make a curve unusable
+    pub fn validate(&self) -> Result<(), MovementProfileLoaderError> {
+        fn check(valid: bool, field: &'static str) -> Result<(), MovementProfileLoaderError> {
+            if valid {
+                Ok(())
+            } else {
+                Err(MovementProfileLoaderError::Invalid(field))
+            }
+        }
+        // Easing needs the control points' x inside 0..=1
+        fn check_curve(
+            curve: &CurveControls,
+            field: &'static str,
+        ) -> Result<(), MovementProfileLoaderError> {
+            check(
+                curve
+                    .iter()
+                    .all(|[x, y]| (0. ..=1.).contains(x) && y.is_finite()),
+                field,
+            )
+        }
+
+        check(self.max_slope_angle.is_finite(), "max_slope_angle")?;
+        check(self.run.duration > 0, "run.duration")?;
+        check_curve(&self.run.curve, "run.curve")?;
+        check(self.jump.time_to_apex > 0, "jump.time_to_apex")?;
+        check(
+            self.jump.release_gravity_multiplier >= 0.,
+            "jump.release_gravity_multiplier",
+        )?;
+        check(
+            self.jump.apex_gravity_multiplier >= 0.,
+            "jump.apex_gravity_multiplier",
+        )?;
+        check(self.fall.duration > 0, "fall.duration")?;
+        check_curve(&self.fall.curve, "fall.curve")?;
+        if let Some(crouch) = &self.crouch {
+            check(crouch.height > 0. && crouch.height <= 1., "crouch.height")?;
+            check(crouch.slide_duration > 0, "crouch.slide_duration")?;
+            check_curve(&crouch.slide_curve, "crouch.slide_curve")?;
+        }
+        Ok(())
+    }
+    pub fn run_curve(&self) -> RunCurve {
+        RunCurve(bezier(&self.run.curve))
+    }
+    pub fn fall_curve(&self) -> FallCurve {
+        FallCurve(bezier(&self.fall.curve))
+    }
+}
 
+#[derive(Default)]
+pub struct MovementProfileLoader;
+
+#[derive(Debug, Error)]
+pub enum MovementProfileLoaderError {
+    #[error("Could not read movement profile: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse movement profile: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("Movement profile has an invalid {0}")]
+    Invalid(&'static str),
+}
+
+impl AssetLoader for MovementProfileLoader {
+    type Asset = MovementProfile;
+    type Settings = ();
+    type Error = MovementProfileLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        // A profile that fails to reload leaves the previous one in place
+        let profile: MovementProfile = ron::de::from_bytes(&bytes)?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["movement.ron"]
+    }
+}
+
+/// The name of the [`MovementProfile`] a character uses, `name` loads
+/// `assets/movement/name.movement.ron`
+#[derive(Component, Clone, Debug)]
+pub struct MovementProfileName(pub String);
+
+impl MovementProfileName {
+    pub fn new(name: &str) -> Self {
+        Self(name.to_string())
+    }
+    /// Reads the `Movement_profile` field of an LDtk entity, falling back to `default`
+    pub fn from_entity_instance(entity_instance: &EntityInstance, default: &str) -> Self {
+        match entity_instance.get_string_field("Movement_profile") {
+            Ok(name) => Self(name.clone()),
+            Err(_) => Self::new(default),
+        }
+    }
+    pub fn path(&self) -> String {
+        format!("movement/{}.movement.ron", self.0)
+    }
+}
+
+/// The loaded [`MovementProfile`] of a character
+#[derive(Component)]
+pub struct MovementProfileHandle(pub Handle<MovementProfile>);
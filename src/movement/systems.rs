@@ -1,7 +1,9 @@
 use avian2d::{math::*, prelude::*};
-use bevy::{ecs::query::Has, prelude::*};
+use std::time::Duration;
 
-use crate::{Facing, Player};
+use bevy::{ecs::query::Has, prelude::*, utils::HashSet};
+
+use crate::{Facing, GravityZone, Ladder, Liquid, MovingPlatform, OneWayPlatform, Player};
 
 use super::components::*;
 
@@ -10,92 +12,241 @@ pub struct CharacterControllerPlugin;
 impl Plugin for CharacterControllerPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<MaxJumpCount>()
-            .insert_resource(Gravity(Vec2::NEG_Y * 1200.))
+            // Every controller applies its own gravity, see `GravityZone`
+            .insert_resource(Gravity(Vector::ZERO))
             .add_event::<MovementEvent>()
+            .add_event::<KnockbackEvent>()
+            .init_asset::<MovementProfile>()
+            .init_asset_loader::<MovementProfileLoader>()
+            .add_systems(FixedFirst, restore_physics_transform)
+            // Runs on the fixed clock right before avian steps in `FixedPostUpdate`, so movement
+            // is frame rate independent
             .add_systems(
-                Update,
+                FixedUpdate,
                 (
+                    update_drop_through,
+                    update_gravity_zone,
                     update_grounded,
+                    update_touching_wall,
+                    update_on_ladder,
+                    update_submerged,
+                    start_knockback,
+                    velocity_to_local,
                     movement_validation,
+                    crouch,
                     jump_fall,
                     run,
+                    hitstun,
                     apply_air_ground_movement_damping,
+                    apply_swim_damping,
+                    velocity_to_world,
+                    apply_platform_velocity,
                 )
                     .chain(),
+            )
+            .add_systems(Update, interpolate_transform)
+            .add_systems(
+                PreUpdate,
+                (load_movement_profiles, apply_movement_profiles).chain(),
             );
     }
 }
 
+/// Puts the [`Transform`] back on the physics [`Position`] before the next physics step,
+/// so the interpolated transform never gets synced back into physics.
+pub fn restore_physics_transform(
+    parents: Query<&GlobalTransform>,
+    mut query: Query<(
+        &Position,
+        &mut Transform,
+        &mut PreviousPosition,
+        Option<&Parent>,
+    )>,
+) {
+    for (position, mut transform, mut previous_position, parent) in query.iter_mut() {
+        let translation = to_parent_space(position.0, parent, &parents);
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+        previous_position.0 = Some(position.0);
+    }
+}
+
+/// Smooths the [`Transform`] between the last two physics steps.
+pub fn interpolate_transform(
+    parents: Query<&GlobalTransform>,
+    mut query: Query<(
+        &Position,
+        &mut Transform,
+        &PreviousPosition,
+        Option<&Parent>,
+    )>,
+    fixed_time: Res<Time<Fixed>>,
+) {
+    let overstep = fixed_time.overstep_fraction();
+    for (position, mut transform, previous_position, parent) in query.iter_mut() {
+        if let Some(previous) = previous_position.0 {
+            let interpolated = previous.lerp(position.0, overstep);
+            let translation = to_parent_space(interpolated, parent, &parents);
+            transform.translation.x = translation.x;
+            transform.translation.y = translation.y;
+        }
+    }
+}
+
+/// Turns a world space [`Position`] into the parent relative space of [`Transform`].
+fn to_parent_space(
+    position: Vector,
+    parent: Option<&Parent>,
+    parents: &Query<&GlobalTransform>,
+) -> Vec3 {
+    match parent.and_then(|parent| parents.get(parent.get()).ok()) {
+        Some(parent_transform) => parent_transform
+            .affine()
+            .inverse()
+            .transform_point3(position.extend(0.)),
+        None => position.extend(0.),
+    }
+}
+
+/// Ticks [`DropThrough`] timers and removes them once they finish.
+pub fn update_drop_through(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut DropThrough)>,
+) {
+    for (entity, mut drop_through) in query.iter_mut() {
+        drop_through.timer.tick(time.delta());
+        if drop_through.timer.finished() {
+            commands.entity(entity).remove::<DropThrough>();
+        }
+    }
+}
+
+type GroundedCharacter<'a> = (
+    Entity,
+    &'a ShapeHits,
+    (&'a Rotation, &'a UpVector),
+    &'a mut HangTime,
+    &'a mut JumpBuffer,
+    &'a mut JumpFallCounter,
+    (&'a mut JumpTimer, &'a mut JumpReleased),
+    &'a mut FallTimer,
+    &'a mut JumpFallState,
+    &'a mut AirDashCounter,
+    Option<&'a MaxSlopeAngle>,
+    Option<&'a DropThrough>,
+    (
+        &'a mut LinearVelocity,
+        &'a mut PlatformVelocity,
+        &'a mut GroundContact,
+    ),
+    Has<Grounded>,
+);
+
 /// Updates the [`Grounded`] status for character controllers.
 pub fn update_grounded(
     mut commands: Commands,
     time: Res<Time>,
     grounds: Query<Option<&RigidBody>, (With<Collider>, Without<Player>)>,
-    mut query: Query<
-        (
-            Entity,
-            &ShapeHits,
-            &Rotation,
-            &mut HangTime,
-            &mut JumpFallCounter,
-            &mut JumpTimer,
-            &mut FallTimer,
-            &mut JumpFallState,
-            Option<&MaxSlopeAngle>,
-            Has<Grounded>,
-        ),
-        With<CharacterController>,
-    >,
+    one_way_platforms: Query<&OneWayPlatform>,
+    moving_platforms: Query<&LinearVelocity, (With<MovingPlatform>, Without<CharacterController>)>,
+    mut query: Query<GroundedCharacter, (With<CharacterController>, Without<MovingPlatform>)>,
 ) {
     for (
         entity,
         hits,
-        rotation,
+        (rotation, up_vector),
         mut hang_time,
+        mut jump_buffer,
         mut jump_fall_counter,
-        mut jump_timer,
+        (mut jump_timer, mut jump_released),
         mut fall_timer,
         mut jump_fall_state,
+        mut air_dash_counter,
         max_slope_angle,
+        drop_through,
+        (mut linear_velocity, mut platform_velocity, mut ground_contact),
         is_already_grounded,
     ) in &mut query
     {
         // The character is grounded if the shape caster has a hit with a normal
         // that isn't too steep.
-        let mut rigid_hits = hits.iter().filter(|hit| {
-            let ground_hits = grounds.get(hit.entity);
-            match ground_hits {
-                Ok(hit) => hit.is_some(),
-                Err(_) => false,
-            }
-        });
-        let is_grounded = rigid_hits.any(|hit| {
-            //let is_grounded = hits.iter().any(|hit| {
+        let rigid_hits = hits
+            .iter()
+            .filter(|hit| {
+                // One-way platforms don't count while passing through them
+                if let Ok(one_way_platform) = one_way_platforms.get(hit.entity) {
+                    if one_way_platform.0.contains(&entity)
+                        || drop_through.is_some_and(|drop| drop.is_dropping_through(hit.entity))
+                    {
+                        return false;
+                    }
+                }
+                let ground_hits = grounds.get(hit.entity);
+                match ground_hits {
+                    Ok(hit) => hit.is_some(),
+                    Err(_) => false,
+                }
+            })
+            .collect::<Vec<&ShapeHitData>>();
+        let is_walkable = |hit: &ShapeHitData| {
             if let Some(angle) = max_slope_angle {
-                (rotation * -hit.normal2).angle_between(Vector::Y).abs() <= angle.0
+                (rotation * -hit.normal2).angle_between(*up_vector.0).abs() <= angle.0
             } else {
                 true
             }
+        };
+        let closest_hit = rigid_hits
+            .iter()
+            .min_by(|hit1, hit2| hit1.time_of_impact.total_cmp(&hit2.time_of_impact));
+        // Kept relative to the character so slopes line up with the local velocity
+        ground_contact.0 = closest_hit.map(|hit| GroundSurface {
+            normal: up_vector.to_local(rotation * -hit.normal2),
+            distance: hit.time_of_impact,
+            is_steep: !is_walkable(hit),
         });
+        let ground_hit = rigid_hits.iter().find(|hit| is_walkable(hit));
+        let is_grounded = ground_hit.is_some();
+
+        // Swap last step's platform velocity for the one currently stood on
+        linear_velocity.0 -= platform_velocity.0;
+        platform_velocity.0 = ground_hit
+            .and_then(|hit| moving_platforms.get(hit.entity).ok())
+            .map_or(Vector::ZERO, |platform_linear_velocity| {
+                platform_linear_velocity.0
+            });
 
         if is_grounded {
             //info!("This fucker is on the ground");
             commands.entity(entity).insert(Grounded);
             if !is_already_grounded {
                 jump_fall_counter.0 = 0;
+                air_dash_counter.0 = 0;
                 jump_timer.0.reset();
                 fall_timer.0.reset();
                 *jump_fall_state = JumpFallState::Idle;
                 hang_time.0.pause();
                 hang_time.0.reset();
+                // Fire a jump that was pressed just before landing, already cut short if
+                // it was let go since
+                if jump_buffer.is_buffered() {
+                    jump_fall_counter.0 += 1;
+                    jump_released.0 = jump_buffer.released;
+                    *jump_fall_state = JumpFallState::Jumping;
+                }
+                jump_buffer.clear();
             }
         } else {
-            if *jump_fall_state != JumpFallState::Jumping {
+            if !matches!(
+                *jump_fall_state,
+                JumpFallState::Jumping | JumpFallState::WallJumping(_)
+            ) {
                 *jump_fall_state = JumpFallState::Falling;
             }
             // Having to tick timers manually is stupid. Like why? Seriously if I have 100 timers, I
             // have to tick each individually? Be so for real
             hang_time.0.tick(time.delta());
+            jump_buffer.timer.tick(time.delta());
             if is_already_grounded {
                 hang_time.0.unpause();
             }
@@ -105,78 +256,511 @@ pub fn update_grounded(
     }
 }
 
-pub fn jump_fall(
-    mut query: Query<(
-        &mut LinearVelocity,
-        &mut JumpTimer,
-        &mut JumpFallState,
-        &JumpHeight,
-        &JumpCurve,
-        &mut FallTimer,
-        &InitialFallSpeed,
-        &MaxFallSpeed,
-        &FallCurve,
-    )>,
-    time: Res<Time>,
+/// Updates the [`TouchingWall`] status for character controllers.
+pub fn update_touching_wall(
+    mut commands: Commands,
+    spatial_query: SpatialQuery,
+    one_way_platforms: Query<(), With<OneWayPlatform>>,
+    query: Query<(Entity, &WallCaster, &Position, &Rotation, &UpVector), With<CharacterController>>,
+) {
+    for (entity, wall_caster, position, rotation, up_vector) in query.iter() {
+        let filter =
+            SpatialQueryFilter::from_mask(GameLayer::GROUND).with_excluded_entities([entity]);
+        // A wall is a hit with a normal (mostly) across the character's up.
+        // One-way platforms share the ground layer but their sides are never walls
+        let touches_wall = |direction: Dir2| {
+            spatial_query
+                .shape_hits(
+                    &wall_caster.shape,
+                    position.0,
+                    rotation.as_radians(),
+                    direction,
+                    wall_caster.max_time_of_impact,
+                    10,
+                    true,
+                    filter.clone(),
+                )
+                .into_iter()
+                .filter(|hit| !one_way_platforms.contains(hit.entity))
+                .min_by(|hit1, hit2| hit1.time_of_impact.total_cmp(&hit2.time_of_impact))
+                .is_some_and(|hit| {
+                    let normal = up_vector.to_local(hit.normal1);
+                    normal.x.abs() > normal.y.abs()
+                })
+        };
+
+        let right = Dir2::new_unchecked(up_vector.right());
+        if touches_wall(-right) {
+            commands.entity(entity).insert(TouchingWall(Facing::Left));
+        } else if touches_wall(right) {
+            commands.entity(entity).insert(TouchingWall(Facing::Right));
+        } else {
+            commands.entity(entity).remove::<TouchingWall>();
+        }
+    }
+}
+
+/// Points characters inside a [`GravityZone`] along the zone's gravity and turns them upright.
+/// Outside of a zone gravity is back to normal.
+pub fn update_gravity_zone(
+    zones: Query<&GravityZone>,
+    mut query: Query<
+        (
+            &CollidingEntities,
+            &mut UpVector,
+            &mut GravityStrength,
+            &mut Rotation,
+        ),
+        With<CharacterController>,
+    >,
+) {
+    for (colliding_entities, mut up_vector, mut gravity_strength, mut rotation) in query.iter_mut()
+    {
+        let (up, strength) = colliding_entities
+            .iter()
+            .find_map(|colliding_entity| zones.get(*colliding_entity).ok())
+            .map_or((Dir2::Y, 1.), |zone| (zone.up, zone.strength));
+        gravity_strength.0 = strength;
+        if up_vector.0 != up {
+            up_vector.0 = up;
+            *rotation = up_vector.rotation();
+        }
+    }
+}
+
+/// Turns velocity relative to the character's [`UpVector`], so the movement systems
+/// can treat +Y as up whichever way gravity points.
+pub fn velocity_to_local(mut query: Query<(&mut LinearVelocity, &UpVector)>) {
+    for (mut linear_velocity, up_vector) in query.iter_mut() {
+        linear_velocity.0 = up_vector.to_local(linear_velocity.0);
+    }
+}
+
+/// Turns velocity back into world space before the physics step.
+pub fn velocity_to_world(mut query: Query<(&mut LinearVelocity, &UpVector)>) {
+    for (mut linear_velocity, up_vector) in query.iter_mut() {
+        linear_velocity.0 = up_vector.to_world(linear_velocity.0);
+    }
+}
+
+/// Updates the [`OnLadder`] status for character controllers and lets go of
+/// ladders that are left behind.
+pub fn update_on_ladder(
+    mut commands: Commands,
+    ladders: Query<(), With<Ladder>>,
+    mut query: Query<
+        (Entity, &CollidingEntities, &mut MoveState, Has<Grounded>),
+        With<CharacterController>,
+    >,
+) {
+    for (entity, colliding_entities, mut move_state, is_grounded) in query.iter_mut() {
+        let is_on_ladder = colliding_entities
+            .iter()
+            .any(|colliding_entity| ladders.contains(*colliding_entity));
+        if is_on_ladder {
+            commands.entity(entity).insert(OnLadder);
+        } else {
+            commands.entity(entity).remove::<OnLadder>();
+        }
+
+        // Climbed off the top or bottom of the ladder
+        if let MoveState::Climbing(direction) = *move_state {
+            if !is_on_ladder || (is_grounded && direction < 0.) {
+                *move_state = MoveState::Idle;
+            }
+        }
+    }
+}
+
+type SwimmingCharacter<'a> = (
+    Entity,
+    &'a CollidingEntities,
+    &'a mut JumpFallCounter,
+    &'a mut AirDashCounter,
+    Has<Submerged>,
+);
+
+/// Updates the [`Submerged`] status for character controllers.
+/// Diving in refreshes jumps and air dashes like landing does.
+pub fn update_submerged(
+    mut commands: Commands,
+    liquids: Query<&Liquid>,
+    mut query: Query<SwimmingCharacter, With<CharacterController>>,
+) {
+    for (entity, colliding_entities, mut jump_fall_counter, mut air_dash_counter, was_submerged) in
+        query.iter_mut()
+    {
+        let density = colliding_entities
+            .iter()
+            .filter_map(|colliding_entity| liquids.get(*colliding_entity).ok())
+            .map(|liquid| liquid.density)
+            .reduce(f32::max);
+        match density {
+            Some(density) => {
+                commands.entity(entity).insert(Submerged(density));
+                if !was_submerged {
+                    jump_fall_counter.0 = 0;
+                    air_dash_counter.0 = 0;
+                }
+            }
+            None => {
+                commands.entity(entity).remove::<Submerged>();
+            }
+        }
+    }
+}
+
+type KnockedBackCharacter<'a> = (
+    &'a mut LinearVelocity,
+    &'a mut MoveState,
+    &'a mut JumpFallState,
+    &'a mut Facing,
+    (&'a mut RunTimer, &'a mut DashTimer, &'a mut FallTimer),
+    &'a UpVector,
+);
+
+/// Starts a [`Hitstun`] for every [`KnockbackEvent`], cancelling whatever the character was doing.
+pub fn start_knockback(
+    mut commands: Commands,
+    mut knockback_event_reader: EventReader<KnockbackEvent>,
+    mut query: Query<KnockedBackCharacter>,
 ) {
+    for event in knockback_event_reader.read() {
+        let Ok((
+            mut linear_velocity,
+            mut move_state,
+            mut jump_fall_state,
+            mut facing,
+            (mut run_timer, mut dash_timer, mut fall_timer),
+            up_vector,
+        )) = query.get_mut(event.entity)
+        else {
+            continue;
+        };
+        linear_velocity.0 = event.direction * event.speed;
+        // `hitstun` runs on velocity relative to the character
+        let velocity = up_vector.to_local(linear_velocity.0);
+        run_timer.0.reset();
+        dash_timer.0.reset();
+        fall_timer.0.reset();
+        *move_state = MoveState::Idle;
+        *jump_fall_state = JumpFallState::Falling;
+        // Face whatever did the hitting
+        if velocity.x > 0. {
+            *facing = Facing::Left;
+        } else if velocity.x < 0. {
+            *facing = Facing::Right;
+        }
+        commands.entity(event.entity).insert(Hitstun {
+            velocity,
+            timer: Timer::new(Duration::from_millis(event.hitstun), TimerMode::Once),
+        });
+    }
+}
+
+type StunnedCharacter<'a> = (
+    Entity,
+    &'a mut Hitstun,
+    &'a mut LinearVelocity,
+    &'a KnockbackCurve,
+    (
+        &'a JumpHeight,
+        &'a JumpTimer,
+        &'a MaxFallSpeed,
+        &'a GravityStrength,
+    ),
+    Has<Submerged>,
+);
+
+/// Moves characters in [`Hitstun`] and hands control back once it's over.
+/// The knockback eases out along the [`KnockbackCurve`] while gravity keeps pulling.
+pub fn hitstun(mut commands: Commands, mut query: Query<StunnedCharacter>, time: Res<Time>) {
+    for (
+        entity,
+        mut hitstun,
+        mut linear_velocity,
+        knockback_curve,
+        (jump_height, jump_timer, max_fall_speed, gravity_strength),
+        is_submerged,
+    ) in query.iter_mut()
+    {
+        hitstun.timer.tick(time.delta());
+        if hitstun.timer.finished() {
+            // Whatever velocity is left carries into the fall and the next run
+            commands.entity(entity).remove::<Hitstun>();
+            continue;
+        }
+        let ease = knockback_curve.0.ease(hitstun.timer.fraction_remaining());
+        linear_velocity.x = hitstun.velocity.x * ease;
+        if is_submerged {
+            linear_velocity.y = hitstun.velocity.y * ease;
+        } else {
+            let time_to_apex = jump_timer.0.duration().as_secs_f32().max(Scalar::EPSILON);
+            linear_velocity.y = (linear_velocity.y
+                - jump_height.gravity(time_to_apex) * gravity_strength.0 * time.delta_seconds())
+            .max(max_fall_speed.0 * gravity_strength.0);
+        }
+    }
+}
+
+type CrouchingCharacter<'a> = (
+    Entity,
+    Ref<'a, Crouching>,
+    &'a CrouchShapes,
+    (&'a mut Collider, &'a mut ShapeCaster, &'a mut WallCaster),
+    (&'a Position, &'a Rotation),
+    &'a mut MoveState,
+    &'a mut RunTimer,
+);
+
+/// Swaps characters between their standing and crouching [`CrouchShapes`].
+/// Standing back up waits until the headroom above the crouched shape is clear.
+pub fn crouch(
+    mut commands: Commands,
+    spatial_query: SpatialQuery,
+    one_way_platforms: Query<(), With<OneWayPlatform>>,
+    mut query: Query<CrouchingCharacter>,
+) {
+    for (
+        entity,
+        crouching,
+        crouch_shapes,
+        (mut collider, mut ground_caster, mut wall_caster),
+        (position, rotation),
+        mut move_state,
+        mut run_timer,
+    ) in query.iter_mut()
+    {
+        let shape = if crouching.is_added() {
+            &crouch_shapes.crouching
+        } else if crouching.wants_to_stand {
+            let filter =
+                SpatialQueryFilter::from_mask(GameLayer::GROUND).with_excluded_entities([entity]);
+            // One-way platforms can be stood up through
+            let is_blocked = spatial_query
+                .shape_intersections(
+                    &crouch_shapes.headroom,
+                    position.0,
+                    rotation.as_radians(),
+                    filter,
+                )
+                .into_iter()
+                .any(|hit| !one_way_platforms.contains(hit));
+            if is_blocked {
+                continue;
+            }
+            commands.entity(entity).remove::<Crouching>();
+            if matches!(*move_state, MoveState::Sliding { .. }) {
+                run_timer.0.reset();
+                *move_state = MoveState::Idle;
+            }
+            &crouch_shapes.standing
+        } else {
+            continue;
+        };
+        *collider = shape.collider.clone();
+        ground_caster.shape = shape.ground_caster.clone();
+        wall_caster.shape = shape.wall_caster.clone();
+    }
+}
+
+type JumpingCharacter<'a> = (
+    &'a mut LinearVelocity,
+    &'a mut JumpTimer,
+    &'a mut JumpFallState,
+    (&'a JumpHeight, &'a JumpHeightMultiplier),
+    (&'a mut JumpReleased, &'a JumpReleaseGravity, &'a ApexHang),
+    &'a mut FallTimer,
+    &'a InitialFallSpeed,
+    &'a MaxFallSpeed,
+    &'a FallCurve,
+    (
+        &'a mut WallJumpTimer,
+        &'a WallJumpSpeed,
+        &'a WallJumpCurve,
+        &'a MaxWallSlideSpeed,
+        Has<TouchingWall>,
+    ),
+    &'a MoveState,
+    (
+        &'a GroundContact,
+        &'a GroundSnapDistance,
+        &'a GravityStrength,
+    ),
+    (Option<&'a Submerged>, &'a mut SwimStroke, &'a Buoyancy),
+);
+
+pub fn jump_fall(mut query: Query<JumpingCharacter, Without<Hitstun>>, time: Res<Time>) {
     for (
         mut linear_velocity,
         mut jump_timer,
         mut jump_fall_state,
-        jump_height,
-        jump_curve,
+        (jump_height, jump_height_multiplier),
+        (mut jump_released, jump_release_gravity, apex_hang),
         mut fall_timer,
         initial_fall_speed,
         max_fall_speed,
         fall_curve,
+        (
+            mut wall_jump_timer,
+            wall_jump_speed,
+            wall_jump_curve,
+            max_wall_slide_speed,
+            is_touching_wall,
+        ),
+        move_state,
+        (ground_contact, ground_snap_distance, gravity_strength),
+        (submerged, mut swim_stroke, buoyancy),
     ) in query.iter_mut()
     {
+        // Gravity is suspended for the length of a dash and while climbing
+        if matches!(
+            move_state,
+            MoveState::Dashing { .. } | MoveState::Climbing(_)
+        ) {
+            fall_timer.0.reset();
+            linear_velocity.y = 0.;
+            continue;
+        }
+        // Liquids replace gravity with a drift towards sinking or floating
+        if let Some(Submerged(density)) = submerged {
+            fall_timer.0.reset();
+            jump_timer.0.reset();
+            jump_released.0 = false;
+            swim_stroke.cooldown.tick(time.delta());
+            // Strokes can go faster than the drift, damping slows them back down
+            if linear_velocity.y.abs() < buoyancy.max_speed {
+                let pull = buoyancy.float * density - buoyancy.sink;
+                linear_velocity.y = (linear_velocity.y + pull * time.delta_seconds())
+                    .clamp(-buoyancy.max_speed, buoyancy.max_speed);
+            }
+            continue;
+        }
         //info!("Jump Fall State: {:?}", jump_fall_state);
         match *jump_fall_state {
             JumpFallState::Jumping => {
+                let time_to_apex = jump_timer.0.duration().as_secs_f32().max(Scalar::EPSILON);
+                let gravity = jump_height.gravity(time_to_apex);
+                // The jump just started, the multiplier changes the height while gravity stays
+                if jump_timer.0.elapsed().is_zero() {
+                    let height = jump_height.0 * jump_height_multiplier.0.max(0.);
+                    linear_velocity.y = apex_hang.launch_velocity(height, gravity);
+                }
                 jump_timer.0.tick(time.delta());
-                if jump_timer.0.finished() {
+                let gravity_multiplier = if jump_released.0 {
+                    jump_release_gravity.0
+                } else if linear_velocity.y < apex_hang.speed {
+                    apex_hang.gravity_multiplier
+                } else {
+                    1.
+                };
+                linear_velocity.y -=
+                    gravity * gravity_multiplier * gravity_strength.0 * time.delta_seconds();
+                if linear_velocity.y <= 0. {
                     *jump_fall_state = JumpFallState::Falling;
-                    return;
                 }
-                // TODO: Jump Curve should goto zero, not accelerate (duh)
-                linear_velocity.y =
-                    jump_height.0 * jump_curve.0.ease(jump_timer.0.fraction_remaining());
+            }
+            JumpFallState::WallJumping(direction) => {
+                wall_jump_timer.0.tick(time.delta());
+                if wall_jump_timer.0.finished() {
+                    wall_jump_timer.0.reset();
+                    *jump_fall_state = JumpFallState::Falling;
+                    continue;
+                }
+                let kick = wall_jump_curve
+                    .0
+                    .ease(wall_jump_timer.0.fraction_remaining());
+                linear_velocity.x = direction * wall_jump_speed.0.x * kick;
+                linear_velocity.y = wall_jump_speed.0.y * kick;
             }
             JumpFallState::Falling => {
                 jump_timer.0.reset();
-                fall_timer.0.tick(time.delta());
-                let new_velocity = max_fall_speed.0 * fall_curve.0.ease(fall_timer.0.fraction());
+                jump_released.0 = false;
+                // Fall slower while still inside the apex band
+                let fall_delta = time.delta().mul_f32(gravity_strength.0);
+                if linear_velocity.y > -apex_hang.speed {
+                    fall_timer
+                        .0
+                        .tick(fall_delta.mul_f32(apex_hang.gravity_multiplier.max(0.)));
+                } else {
+                    fall_timer.0.tick(fall_delta);
+                }
+                let mut new_velocity = max_fall_speed.0
+                    * gravity_strength.0
+                    * fall_curve.0.ease(fall_timer.0.fraction());
+                // Wall slide
+                if is_touching_wall {
+                    new_velocity = new_velocity.max(max_wall_slide_speed.0);
+                }
                 linear_velocity.y = linear_velocity.y.lerp(new_velocity, 0.1);
+                // Slide down slopes that are too steep to stand on
+                if let Some(surface) = ground_contact.0 {
+                    if surface.is_steep && surface.distance <= ground_snap_distance.0 {
+                        linear_velocity.0 = surface.downhill() * linear_velocity.y.abs();
+                    }
+                }
             }
             JumpFallState::Idle => {}
         }
     }
 }
 
-pub fn run(
-    mut query: Query<(
-        &mut LinearVelocity,
-        &mut MoveState,
-        &mut RunTimer,
-        &InitialRunSpeed,
-        &MaxRunSpeed,
-        &RunCurve,
-        &GroundMovementDampingFactor,
-    )>,
-    time: Res<Time>,
-) {
+type RunningCharacter<'a> = (
+    &'a mut LinearVelocity,
+    &'a mut MoveState,
+    &'a mut RunTimer,
+    &'a InitialRunSpeed,
+    &'a MaxRunSpeed,
+    &'a RunCurve,
+    &'a GroundMovementDampingFactor,
+    &'a JumpFallState,
+    (
+        &'a mut DashTimer,
+        &'a mut DashCooldown,
+        &'a DashSpeed,
+        &'a DashCurve,
+    ),
+    &'a ClimbSpeed,
+    (&'a GroundContact, &'a GroundSnapDistance, Has<Grounded>),
+    (&'a SwimSpeed, Has<Submerged>, Has<Hitstun>),
+    (
+        Has<Crouching>,
+        &'a CrouchSpeed,
+        &'a mut SlideTimer,
+        &'a SlideCurve,
+    ),
+);
+
+pub fn run(mut query: Query<RunningCharacter>, time: Res<Time>) {
     for (
         mut linear_velocity,
-        move_state,
+        mut move_state,
         mut run_timer,
         initial_run_speed,
         max_run_speed,
         run_curve,
         damping_factor,
+        jump_fall_state,
+        (mut dash_timer, mut dash_cooldown, dash_speed, dash_curve),
+        climb_speed,
+        (ground_contact, ground_snap_distance, is_grounded),
+        (swim_speed, is_submerged, is_stunned),
+        (is_crouching, crouch_speed, mut slide_timer, slide_curve),
     ) in query.iter_mut()
     {
+        dash_cooldown.0.tick(time.delta());
+        // Knockback owns velocity until the hitstun ends
+        if is_stunned {
+            continue;
+        }
+        // The wall jump owns horizontal velocity until it finishes
+        if matches!(jump_fall_state, JumpFallState::WallJumping(_)) {
+            continue;
+        }
         match *move_state {
+            MoveState::Running(direction) if is_submerged => {
+                linear_velocity.x = linear_velocity.x.lerp(direction * swim_speed.0, 0.1);
+            }
             MoveState::Running(direction) => {
                 let previous_velocity = direction
                     * (initial_run_speed.0
@@ -184,36 +768,105 @@ pub fn run(
                 // Simulate Damping
                 //previous_velocity *= damping_factor.0;
                 run_timer.0.tick(time.delta());
-                let new_velocity = direction
+                let mut new_velocity = direction
                     * (initial_run_speed.0
                         + max_run_speed.0 * run_curve.0.ease(run_timer.0.fraction()));
+                if is_crouching {
+                    new_velocity = new_velocity.clamp(-crouch_speed.0, crouch_speed.0);
+                }
                 //info!("Expected: {}", previous_velocity);
                 //info!("Current: {}", linear_velocity.x);
                 //info!("New: {}\n", new_velocity);
-                run_timer.0.tick(time.delta());
-                linear_velocity.x = new_velocity;
+                match ground_contact.0 {
+                    // Follow the slope instead of launching off or pushing into it
+                    Some(surface)
+                        if is_grounded
+                            && !surface.is_steep
+                            && *jump_fall_state == JumpFallState::Idle =>
+                    {
+                        linear_velocity.0 = surface.tangent() * new_velocity;
+                        let gap = surface.distance - GROUND_SKIN;
+                        if gap > 0. && gap <= ground_snap_distance.0 {
+                            linear_velocity.y -= gap / time.delta_seconds().adjust_precision();
+                        }
+                    }
+                    // Too steep to run on, the slide in `jump_fall` takes over
+                    Some(surface)
+                        if surface.is_steep && surface.distance <= ground_snap_distance.0 => {}
+                    _ => linear_velocity.x = new_velocity,
+                }
+            }
+            MoveState::Dashing { direction, resume } => {
+                dash_timer.0.tick(time.delta());
+                if dash_timer.0.finished() {
+                    dash_timer.0.reset();
+                    *move_state = match resume {
+                        Some(run_direction) => MoveState::Running(run_direction),
+                        None => {
+                            run_timer.0.reset();
+                            MoveState::Idle
+                        }
+                    };
+                    continue;
+                }
+                linear_velocity.x =
+                    direction * dash_speed.0 * dash_curve.0.ease(dash_timer.0.fraction_remaining());
+            }
+            MoveState::Climbing(direction) => {
+                linear_velocity.x = 0.;
+                linear_velocity.y = direction * climb_speed.0;
+            }
+            MoveState::Sliding { direction, speed } => {
+                slide_timer.0.tick(time.delta());
+                if slide_timer.0.finished() {
+                    slide_timer.0.reset();
+                    run_timer.0.reset();
+                    *move_state = MoveState::Idle;
+                    continue;
+                }
+                linear_velocity.x =
+                    direction * speed * slide_curve.0.ease(slide_timer.0.fraction_remaining());
             }
-            MoveState::Dashing => {}
             MoveState::Idle => {}
         }
     }
 }
 
+type ValidatedController<'a> = (
+    &'a mut JumpFallState,
+    &'a mut MoveState,
+    &'a mut LinearVelocity,
+    &'a HangTime,
+    &'a mut JumpBuffer,
+    &'a mut JumpFallCounter,
+    &'a mut RunTimer,
+    &'a MaxJumpCount,
+    &'a mut Facing,
+    (&'a mut WallJumpTimer, Option<&'a TouchingWall>),
+    (
+        &'a mut DashTimer,
+        &'a mut DashCooldown,
+        &'a MaxAirDashCount,
+        &'a mut AirDashCounter,
+    ),
+    &'a ShapeHits,
+    (&'a mut JumpTimer, &'a mut JumpReleased),
+    (Has<OnLadder>, Has<Submerged>, &'a mut SwimStroke),
+    (
+        Has<Grounded>,
+        Has<Hitstun>,
+        Option<&'a mut Crouching>,
+        (&'a MinSlideSpeed, &'a mut SlideTimer),
+    ),
+);
+
 /// Responds to [`MovementAction`] events and moves character controllers accordingly.
 pub fn movement_validation(
+    mut commands: Commands,
     time: Res<Time>,
     mut movement_event_reader: EventReader<MovementEvent>,
-    mut controllers: Query<(
-        &mut JumpFallState,
-        &mut MoveState,
-        &mut LinearVelocity,
-        &HangTime,
-        &mut JumpFallCounter,
-        &mut RunTimer,
-        &MaxJumpCount,
-        &mut Facing,
-        Has<Grounded>,
-    )>,
+    one_way_platforms: Query<(), With<OneWayPlatform>>,
+    mut controllers: Query<ValidatedController>,
 ) {
     // Precision is adjusted so that the example works with
     // both the `f32` and `f64` features. Otherwise you don't need this.
@@ -225,27 +878,82 @@ pub fn movement_validation(
             mut jump_fall_state,
             mut move_state,
             //jump_impulse,
-            linear_velocity,
+            mut linear_velocity,
             hang_time,
+            mut jump_buffer,
             mut jump_fall_counter,
             mut run_timer,
             max_jump_counter,
             mut facing,
-            is_grounded,
+            (mut wall_jump_timer, touching_wall),
+            (mut dash_timer, mut dash_cooldown, max_air_dash_count, mut air_dash_counter),
+            ground_hits,
+            (mut jump_timer, mut jump_released),
+            (is_on_ladder, is_submerged, mut swim_stroke),
+            (is_grounded, is_stunned, mut crouching, (min_slide_speed, mut slide_timer)),
         )) = event_controller
         {
+            // No control while reeling from a hit
+            if is_stunned {
+                continue;
+            }
             match event.action {
                 // TODO: Decide if this should be RunRight or MoveRight
                 MovementAction::RunRight => {
                     *facing = Facing::Right;
+                    match &mut *move_state {
+                        MoveState::Dashing { resume, .. } => {
+                            *resume = Some(1.);
+                            continue;
+                        }
+                        MoveState::Climbing(_) | MoveState::Sliding { .. } => continue,
+                        _ => {}
+                    }
                     *move_state = MoveState::Running(1.);
                     //linear_velocity.x += *direction * movement_acceleration.0 * delta_time;
                 }
                 MovementAction::RunLeft => {
-                    *move_state = MoveState::Running(-1.);
                     *facing = Facing::Left;
+                    match &mut *move_state {
+                        MoveState::Dashing { resume, .. } => {
+                            *resume = Some(-1.);
+                            continue;
+                        }
+                        MoveState::Climbing(_) | MoveState::Sliding { .. } => continue,
+                        _ => {}
+                    }
+                    *move_state = MoveState::Running(-1.);
                 }
                 MovementAction::JumpStart => {
+                    if matches!(*move_state, MoveState::Climbing(_)) {
+                        // Jump off the ladder
+                        *move_state = MoveState::Idle;
+                        jump_timer.0.reset();
+                        jump_released.0 = false;
+                        jump_fall_counter.0 = 1;
+                        *jump_fall_state = JumpFallState::Jumping;
+                        continue;
+                    }
+                    if is_submerged {
+                        // Swim strokes can be repeated as often as the cooldown allows
+                        if swim_stroke.cooldown.finished() {
+                            swim_stroke.cooldown.reset();
+                            linear_velocity.y = linear_velocity.y.max(swim_stroke.speed);
+                        }
+                        continue;
+                    }
+                    if let (Some(TouchingWall(wall_side)), false) = (touching_wall, is_grounded) {
+                        // Kick away from the wall, a wall jump counts as the base jump
+                        let (direction, away) = match wall_side {
+                            Facing::Left => (1., Facing::Right),
+                            Facing::Right => (-1., Facing::Left),
+                        };
+                        *facing = away;
+                        wall_jump_timer.0.reset();
+                        jump_fall_counter.0 = 1;
+                        *jump_fall_state = JumpFallState::WallJumping(direction);
+                        continue;
+                    }
                     let has_jumps_left = jump_fall_counter.0 < max_jump_counter.0;
                     let is_base_jump = max_jump_counter.0 == jump_fall_counter.0;
                     let still_hanging = !hang_time.0.finished();
@@ -257,10 +965,14 @@ pub fn movement_validation(
                         can_jump = false;
                     }
                     if can_jump {
+                        jump_timer.0.reset();
+                        jump_released.0 = false;
                         jump_fall_counter.0 += 1;
                         *jump_fall_state = JumpFallState::Jumping;
                         //linear_velocity.y = jump_impulse.0;
-                        //linear_velocity.y += jump_height.0 * jump_curve.0.ease(jump_timer.0.fraction());
+                    } else {
+                        // Remember the press in case the ground is close
+                        jump_buffer.buffer();
                     }
                     //info!("Grounded:\t\t{}", is_grounded);
                     //info!("Jump Fall State:\t{:?}", jump_fall_state);
@@ -271,22 +983,140 @@ pub fn movement_validation(
                     //info!("Can Jump:\t\t{}\n", can_jump);
                 }
                 MovementAction::JumpEnd => {
+                    // Cut the jump short with extra gravity rather than stopping dead
                     if *jump_fall_state == JumpFallState::Jumping {
-                        *jump_fall_state = JumpFallState::Falling;
+                        jump_released.0 = true;
+                    }
+                    // A tap released before landing still jumps, but only as a short hop
+                    if jump_buffer.is_buffered() {
+                        jump_buffer.released = true;
                     }
                 }
                 MovementAction::RunEnd => {
+                    if let MoveState::Dashing { resume, .. } = &mut *move_state {
+                        *resume = None;
+                    }
                     if matches!(*move_state, MoveState::Running(_)) {
                         *move_state = MoveState::Idle;
                         run_timer.0.reset();
                     }
                 }
-                MovementAction::Fall => {}
+                MovementAction::Fall => {
+                    // Drop through any one-way platforms being stood on
+                    let platforms = ground_hits
+                        .iter()
+                        .map(|hit| hit.entity)
+                        .filter(|entity| one_way_platforms.contains(*entity))
+                        .collect::<HashSet<Entity>>();
+                    if !is_grounded || platforms.is_empty() {
+                        continue;
+                    }
+                    commands
+                        .entity(event.entity)
+                        .insert(DropThrough::new(platforms, 250));
+                    *jump_fall_state = JumpFallState::Falling;
+                }
+                MovementAction::Dash => {
+                    let is_dashing = matches!(
+                        *move_state,
+                        MoveState::Dashing { .. } | MoveState::Climbing(_)
+                    );
+                    let has_air_dashes_left = air_dash_counter.0 < max_air_dash_count.0;
+                    // The wall jump owns velocity until it finishes
+                    let is_wall_jumping = matches!(*jump_fall_state, JumpFallState::WallJumping(_));
+                    if is_dashing
+                        || is_wall_jumping
+                        || !dash_cooldown.0.finished()
+                        || !(is_grounded || has_air_dashes_left)
+                    {
+                        continue;
+                    }
+                    if !is_grounded {
+                        air_dash_counter.0 += 1;
+                    }
+                    let resume = match *move_state {
+                        MoveState::Running(direction) => Some(direction),
+                        _ => None,
+                    };
+                    let direction = match *facing {
+                        Facing::Left => -1.,
+                        Facing::Right => 1.,
+                    };
+                    dash_timer.0.reset();
+                    dash_cooldown.0.reset();
+                    *move_state = MoveState::Dashing { direction, resume };
+                }
+                MovementAction::ClimbUp | MovementAction::ClimbDown => {
+                    if !is_on_ladder || matches!(*move_state, MoveState::Dashing { .. }) {
+                        continue;
+                    }
+                    let direction = match event.action {
+                        MovementAction::ClimbUp => 1.,
+                        _ => -1.,
+                    };
+                    // Can't climb down into the ground
+                    if is_grounded && direction < 0. {
+                        continue;
+                    }
+                    if let Some(crouching) = &mut crouching {
+                        crouching.wants_to_stand = true;
+                    }
+                    if !matches!(*move_state, MoveState::Climbing(_)) {
+                        run_timer.0.reset();
+                        jump_fall_counter.0 = 0;
+                        air_dash_counter.0 = 0;
+                        *jump_fall_state = JumpFallState::Idle;
+                    }
+                    *move_state = MoveState::Climbing(direction);
+                }
+                MovementAction::ClimbEnd => {
+                    if matches!(*move_state, MoveState::Climbing(_)) {
+                        *move_state = MoveState::Climbing(0.);
+                    }
+                }
+                MovementAction::Crouch => {
+                    if let Some(crouching) = &mut crouching {
+                        crouching.wants_to_stand = false;
+                        continue;
+                    }
+                    if !is_grounded
+                        || is_submerged
+                        || matches!(
+                            *move_state,
+                            MoveState::Dashing { .. } | MoveState::Climbing(_)
+                        )
+                    {
+                        continue;
+                    }
+                    commands.entity(event.entity).insert(Crouching {
+                        wants_to_stand: false,
+                    });
+                    // Crouching out of a fast run turns into a slide
+                    if let MoveState::Running(direction) = *move_state {
+                        let speed = linear_velocity.x.abs();
+                        if speed >= min_slide_speed.0 {
+                            slide_timer.0.reset();
+                            *move_state = MoveState::Sliding { direction, speed };
+                        }
+                    }
+                }
+                MovementAction::CrouchEnd => {
+                    if let Some(crouching) = &mut crouching {
+                        crouching.wants_to_stand = true;
+                    }
+                }
             }
         }
     }
 }
 
+/// Carries characters along with the [`MovingPlatform`] they stand on.
+pub fn apply_platform_velocity(mut query: Query<(&mut LinearVelocity, &PlatformVelocity)>) {
+    for (mut linear_velocity, platform_velocity) in query.iter_mut() {
+        linear_velocity.0 += platform_velocity.0;
+    }
+}
+
 //pub fn apply_movement_damping(
 //    mut query: Query<(
 //        &MovementDampingFactor,
@@ -301,31 +1131,214 @@ pub fn movement_validation(
 //        linear_velocity.x *= 0.5;
 //    }
 //}
+
+type DampedCharacter<'a> = (
+    &'a GroundMovementDampingFactor,
+    &'a AirMovementDampingFactor,
+    &'a mut LinearVelocity,
+    &'a CollidingEntities, //&Collision
+);
+
 /// Slows down movement in the X direction.
 pub fn apply_air_ground_movement_damping(
-    mut query: Query<
-        (
-            &GroundMovementDampingFactor,
-            &AirMovementDampingFactor,
-            &mut LinearVelocity,
-            &CollidingEntities, //&Collision
-        ),
-        With<Player>,
-    >,
+    mut query: Query<DampedCharacter, (With<Player>, Without<Submerged>)>,
     grounds: Query<Option<&RigidBody>, (With<Collider>, Without<Player>)>,
+    time: Res<Time>,
 ) {
+    // Damping factors are tuned per 60th of a second, scale them to the actual step
+    let steps = time.delta_seconds().adjust_precision() * DAMPING_REFERENCE_RATE;
     for (ground_damping_factor, air_damping_factor, mut linear_velocity, colliding_entities) in
         &mut query
     {
         let rigid_hits = colliding_entities
             .iter()
-            .filter(|hit| grounds.get(**hit).unwrap().is_some())
+            .filter(|hit| {
+                // The entity may have been despawned since the collision was recorded
+                let Ok(rigid_body) = grounds.get(**hit) else {
+                    return false;
+                };
+                rigid_body.is_some()
+            })
             .collect::<Vec<&Entity>>();
         // We could use `LinearDamping`, but we don't want to dampen movement along the Y axis
         if rigid_hits.is_empty() {
-            linear_velocity.x *= air_damping_factor.0;
+            linear_velocity.x *= air_damping_factor.0.powf(steps);
         } else {
-            linear_velocity.x *= ground_damping_factor.0;
+            linear_velocity.x *= ground_damping_factor.0.powf(steps);
+        }
+    }
+}
+
+/// Slows down movement on both axes while swimming.
+pub fn apply_swim_damping(
+    mut query: Query<(&SwimDampingFactor, &mut LinearVelocity), With<Submerged>>,
+    time: Res<Time>,
+) {
+    let steps = time.delta_seconds().adjust_precision() * DAMPING_REFERENCE_RATE;
+    for (damping_factor, mut linear_velocity) in &mut query {
+        linear_velocity.0 *= damping_factor.0.powf(steps);
+    }
+}
+
+/// Starts loading the [`MovementProfile`] named by a new [`MovementProfileName`].
+pub fn load_movement_profiles(
+    mut commands: Commands,
+    query: Query<(Entity, &MovementProfileName), Changed<MovementProfileName>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (entity, name) in query.iter() {
+        commands
+            .entity(entity)
+            .insert(MovementProfileHandle(asset_server.load(name.path())));
+    }
+}
+
+type ProfiledCharacter<'a> = (
+    Ref<'a, MovementProfileHandle>,
+    (
+        &'a mut GroundMovementDampingFactor,
+        &'a mut AirMovementDampingFactor,
+        &'a mut MaxSlopeAngle,
+        &'a mut HangTime,
+        &'a mut JumpBuffer,
+    ),
+    (
+        &'a mut InitialRunSpeed,
+        &'a mut MaxRunSpeed,
+        &'a mut RunTimer,
+        &'a mut RunCurve,
+    ),
+    (
+        &'a mut JumpHeight,
+        &'a mut JumpTimer,
+        &'a mut MaxJumpCount,
+        &'a mut JumpReleaseGravity,
+        &'a mut ApexHang,
+    ),
+    (
+        &'a mut InitialFallSpeed,
+        &'a mut MaxFallSpeed,
+        &'a mut FallTimer,
+        &'a mut FallCurve,
+    ),
+    (
+        &'a mut SwimSpeed,
+        &'a mut SwimStroke,
+        &'a mut Buoyancy,
+        &'a mut SwimDampingFactor,
+    ),
+    (
+        &'a mut CrouchShapes,
+        &'a mut CrouchSpeed,
+        &'a mut MinSlideSpeed,
+        &'a mut SlideTimer,
+        &'a mut SlideCurve,
+    ),
+);
+
+/// Copies a [`MovementProfile`] onto the controllers using it, whenever the profile
+/// (re)loads or a controller picks up a profile that has already loaded.
+/// Only the tuning values are touched so characters keep their current movement state.
+pub fn apply_movement_profiles(
+    mut asset_events: EventReader<AssetEvent<MovementProfile>>,
+    profiles: Res<Assets<MovementProfile>>,
+    mut query: Query<ProfiledCharacter>,
+) {
+    let changed: HashSet<AssetId<MovementProfile>> = asset_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (handle, movement, run, jump, fall, swim, crouch) in query.iter_mut() {
+        if !handle.is_changed() && !changed.contains(&handle.0.id()) {
+            continue;
+        }
+        let Some(profile) = profiles.get(&handle.0) else {
+            continue;
+        };
+
+        let (
+            mut ground_damping,
+            mut air_damping,
+            mut max_slope_angle,
+            mut hang_time,
+            mut jump_buffer,
+        ) = movement;
+        ground_damping.0 = profile.ground_damping;
+        air_damping.0 = profile.air_damping;
+        max_slope_angle.0 = profile.max_slope_angle.to_radians();
+        hang_time
+            .0
+            .set_duration(Duration::from_millis(profile.hang_duration));
+        jump_buffer
+            .timer
+            .set_duration(Duration::from_millis(profile.jump_buffer_duration));
+
+        let (mut initial_run_speed, mut max_run_speed, mut run_timer, mut run_curve) = run;
+        initial_run_speed.0 = profile.run.initial_speed;
+        max_run_speed.0 = profile.run.max_speed;
+        run_timer
+            .0
+            .set_duration(Duration::from_millis(profile.run.duration));
+        *run_curve = profile.run_curve();
+
+        let (
+            mut jump_height,
+            mut jump_timer,
+            mut max_jump_count,
+            mut release_gravity,
+            mut apex_hang,
+        ) = jump;
+        jump_height.0 = profile.jump.height;
+        jump_timer
+            .0
+            .set_duration(Duration::from_millis(profile.jump.time_to_apex));
+        max_jump_count.0 = profile.jump.max_jump_count;
+        release_gravity.0 = profile.jump.release_gravity_multiplier;
+        apex_hang.speed = profile.jump.apex_hang_speed;
+        apex_hang.gravity_multiplier = profile.jump.apex_gravity_multiplier;
+
+        let (mut initial_fall_speed, mut max_fall_speed, mut fall_timer, mut fall_curve) = fall;
+        initial_fall_speed.0 = profile.fall.initial_speed;
+        max_fall_speed.0 = profile.fall.max_speed;
+        fall_timer
+            .0
+            .set_duration(Duration::from_millis(profile.fall.duration));
+        *fall_curve = profile.fall_curve();
+
+        if let Some(swim_profile) = &profile.swim {
+            let (mut swim_speed, mut swim_stroke, mut buoyancy, mut swim_damping) = swim;
+            swim_speed.0 = swim_profile.speed;
+            swim_stroke.speed = swim_profile.stroke_speed;
+            swim_stroke
+                .cooldown
+                .set_duration(Duration::from_millis(swim_profile.stroke_cooldown));
+            buoyancy.sink = swim_profile.sink;
+            buoyancy.float = swim_profile.float;
+            buoyancy.max_speed = swim_profile.max_speed;
+            swim_damping.0 = swim_profile.damping;
+        }
+
+        if let Some(crouch_profile) = &profile.crouch {
+            let (
+                mut crouch_shapes,
+                mut crouch_speed,
+                mut min_slide_speed,
+                mut slide_timer,
+                mut slide_curve,
+            ) = crouch;
+            // A character that's already crouching keeps its shape until it next crouches
+            *crouch_shapes =
+                CrouchShapes::new(&crouch_shapes.standing.collider, crouch_profile.height);
+            crouch_speed.0 = crouch_profile.speed;
+            min_slide_speed.0 = crouch_profile.min_slide_speed;
+            slide_timer
+                .0
+                .set_duration(Duration::from_millis(crouch_profile.slide_duration));
+            *slide_curve = crouch_profile.slide_curve();
         }
     }
 }
@@ -1,4 +1,3 @@
-use avian2d::math::Scalar;
 use avian2d::prelude::*;
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
@@ -6,6 +5,7 @@ use bevy_ecs_ldtk::prelude::*;
 use crate::graphics::prelude::*;
 use crate::health_damage::prelude::*;
 use crate::movement::prelude::*;
+use crate::status_effects::prelude::*;
 use crate::InteractorRange;
 
 #[derive(Component)]
@@ -16,16 +16,23 @@ pub struct PlayerBundle {
     name: Name,
     player: Player,
     character_controler: CharacterControllerBundle,
+    #[with(player_movement_profile)]
+    movement_profile: MovementProfileName,
     friction: Friction,
     restitution: Restitution,
     collider_density: ColliderDensity,
-    #[sprite_sheet_bundle("sprites/hehe.png", 10, 20, 6, 3, 1, 0, 0)]
     sprite_sheet_bundle: LdtkSpriteSheetBundle,
     player_graphics_bundle: PlayerGraphicsBundle,
+    aseprite_sheet: AsepriteSheetPath,
     #[worldly]
     worldly: Worldly,
     health: HealthBundle,
     interactor: InteractorRange,
+    status_effects: StatusEffects,
+}
+
+fn player_movement_profile(entity_instance: &EntityInstance) -> MovementProfileName {
+    MovementProfileName::from_entity_instance(entity_instance, "hehe")
 }
 
 impl Default for PlayerBundle {
@@ -33,23 +40,8 @@ impl Default for PlayerBundle {
         Self {
             name: Name::new("Hehe"),
             player: Player,
-            character_controler: CharacterControllerBundle::new(Collider::rectangle(10.0, 20.0))
-                .with_movement(
-                    0.80,
-                    0.50,
-                    RunBundle::new(20., 380., 550, Vec2::new(0.5, 0.5), Vec2::new(0.5, 0.5)),
-                    JumpBundle::new(800., 200, 3, Vec2::new(0., 1.), Vec2::new(0.7, 0.9)),
-                    FallBundle::new(
-                        // TODO: Get rid of initial fall speed??
-                        -0.,
-                        -800.,
-                        550,
-                        Vec2::new(0.0, 1.0),
-                        Vec2::new(0.50, 1.0),
-                    ),
-                    (30.0 as Scalar).to_radians(),
-                    100,
-                ),
+            character_controler: CharacterControllerBundle::new(Collider::rectangle(10.0, 20.0)),
+            movement_profile: MovementProfileName::new("hehe"),
             friction: Friction::ZERO.with_combine_rule(CoefficientCombine::Max),
             restitution: Restitution::ZERO.with_combine_rule(CoefficientCombine::Min),
             collider_density: ColliderDensity(2.0),
@@ -57,31 +49,84 @@ impl Default for PlayerBundle {
             player_graphics_bundle: PlayerGraphicsBundle {
                 facing: Facing::default(),
                 state: GraphicsState::Falling,
+                // Frames come from the tags in hehe.aseprite.json, Idle plays its tag as it is
                 animation_list: AnimationList(std::collections::HashMap::from([
-                    (GraphicsState::Idle, StateAnimation::new_timer(12, 17, 450)),
-                    (GraphicsState::Running, StateAnimation::new_timer(0, 5, 300)),
+                    (
+                        GraphicsState::Running,
+                        StateAnimation::new_sheet_timer()
+                            .with_playback_speed(PlaybackSpeed::HorizontalVelocity(300.)),
+                    ),
                     (
                         GraphicsState::Jumping,
-                        StateAnimation::new_velocity_list(
-                            6,
-                            11,
-                            vec![100., 200., 300., 400., 500.],
-                        ),
+                        StateAnimation::new_sheet_velocity_list(vec![100., 200., 300., 400., 500.]),
                     ),
                     (
                         GraphicsState::Falling,
-                        StateAnimation::new_velocity_list(
-                            11,
-                            6,
-                            vec![-10., -100., -220., -230., -250.],
-                        ),
+                        StateAnimation::new_sheet_velocity_list(vec![
+                            -10., -100., -220., -230., -250.,
+                        ])
+                        .with_sheet_frames(SheetFrames::ReversedTag("Jump".to_string())),
+                    ),
+                    // TODO: hehe has no climbing, swimming or crouching tags yet, borrow the
+                    // run and idle cycles until it does
+                    (
+                        GraphicsState::Climbing,
+                        StateAnimation::new_sheet_timer()
+                            .with_sheet_frames(SheetFrames::Tag("Run".to_string()))
+                            .with_playback_speed(PlaybackSpeed::VerticalVelocity(100.)),
+                    ),
+                    (
+                        GraphicsState::Swimming,
+                        StateAnimation::new_sheet_timer()
+                            .with_sheet_frames(SheetFrames::Tag("Run".to_string())),
+                    ),
+                    (
+                        GraphicsState::Crouching,
+                        StateAnimation::new_sheet_timer()
+                            .with_sheet_frames(SheetFrames::Tag("Idle".to_string())),
+                    ),
+                    // TODO: hehe has no sliding or landing tags yet, borrow the run and jump
+                    // cycles until it does
+                    (
+                        GraphicsState::Sliding,
+                        StateAnimation::new_sheet_timer()
+                            .with_sheet_frames(SheetFrames::Tag("Run".to_string())),
+                    ),
+                    (
+                        GraphicsState::Landing,
+                        StateAnimation::new_sheet_timer()
+                            .with_sheet_frames(SheetFrames::Tag("Jump".to_string())),
                     ),
                 ])),
+                transitions: StateTransitions::default()
+                    .with_via(
+                        GraphicsState::Falling,
+                        GraphicsState::Idle,
+                        GraphicsState::Landing,
+                    )
+                    .with_min_dwell(GraphicsState::Landing, 120)
+                    .with_priority(GraphicsState::Jumping, 1)
+                    .with_priority(GraphicsState::Climbing, 1)
+                    .with_priority(GraphicsState::Swimming, 1),
             },
+            aseprite_sheet: AsepriteSheetPath::new("sprites/hehe.aseprite.json"),
             worldly: Worldly::default(),
-            health: HealthBundle::new(100., [[0.25, 0.1], [0.25, 1.]], 2800)
-                .with_current_health(50.),
+            health: HealthBundle::new(100.)
+                .with_regeneration(5., 3000, 1.)
+                .with_current_health(50.)
+                .with_invulnerability(800)
+                .with_death_policy(DeathPolicy::Respawn)
+                .with_healthbar_style(
+                    HealthbarStyle::default()
+                        .with_gradient(vec![
+                            (0., Color::linear_rgb(1., 0., 0.)),
+                            (0.5, Color::linear_rgb(1., 0.8, 0.)),
+                            (1., Color::linear_rgb(0., 1., 0.2)),
+                        ])
+                        .with_trail(Color::WHITE, 400, 0.5),
+                ),
             interactor: InteractorRange(100.),
+            status_effects: StatusEffects::default(),
         }
     }
 }
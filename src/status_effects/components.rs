@@ -0,0 +1,338 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::DamageType;
+
+/// What happens when an effect is applied to an entity that already has one of the same name
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stacking {
+    /// Restarts the existing effect's duration
+    Refresh,
+    /// Adds a stack to the existing effect, up to `max_stacks`, and restarts its duration.
+    /// Every stack applies the effect's modifiers again
+    Intensity { max_stacks: u32 },
+    /// Runs alongside the existing effect with its own duration
+    Independent,
+}
+
+/// One thing a status effect does while it's active
+#[derive(Clone, Debug)]
+pub enum EffectModifier {
+    /// Deals `amount` of `kind` damage every tick
+    Damage { amount: f32, kind: DamageType },
+    /// Heals `amount` every tick
+    Heal(f32),
+    /// Multiplies [`InitialRunSpeed`](crate::InitialRunSpeed) and [`MaxRunSpeed`](crate::MaxRunSpeed)
+    RunSpeed(f32),
+    /// Multiplies [`JumpHeightMultiplier`](crate::JumpHeightMultiplier)
+    JumpHeight(f32),
+}
+
+/// A timed effect, like poison or a slow, that can be applied to entities with [`StatusEffects`]
+#[derive(Clone, Debug)]
+pub struct StatusEffect {
+    /// Effects with the same name stack with each other
+    pub name: String,
+    pub modifiers: Vec<EffectModifier>,
+    pub duration: Duration,
+    /// How often damage and healing modifiers happen
+    pub tick: Duration,
+    pub stacking: Stacking,
+    /// The colour the sprite is tinted while the effect is active
+    pub tint: Option<Color>,
+}
+
+impl StatusEffect {
+    pub fn new(name: &str, duration: u64, stacking: Stacking) -> Self {
+        Self {
+            name: name.to_string(),
+            modifiers: Vec::new(),
+            duration: Duration::from_millis(duration),
+            tick: Duration::from_millis(500),
+            stacking,
+            tint: None,
+        }
+    }
+    /// Deals `amount` poison damage every tick, stacking up to 5 times
+    pub fn poison(amount: f32, duration: u64) -> Self {
+        Self::new("poison", duration, Stacking::Intensity { max_stacks: 5 })
+            .with_modifier(EffectModifier::Damage {
+                amount,
+                kind: DamageType::POISON,
+            })
+            .with_tint(Color::linear_rgb(0.4, 1., 0.4))
+    }
+    /// Deals `amount` fire damage every tick
+    pub fn burn(amount: f32, duration: u64) -> Self {
+        Self::new("burn", duration, Stacking::Refresh)
+            .with_modifier(EffectModifier::Damage {
+                amount,
+                kind: DamageType::FIRE,
+            })
+            .with_tint(Color::linear_rgb(1., 0.5, 0.3))
+    }
+    /// Multiplies run speed and jump height by `multiplier`
+    pub fn slow(multiplier: f32, duration: u64) -> Self {
+        Self::new("slow", duration, Stacking::Refresh)
+            .with_modifier(EffectModifier::RunSpeed(multiplier))
+            .with_modifier(EffectModifier::JumpHeight(multiplier))
+            .with_tint(Color::linear_rgb(0.5, 0.6, 1.))
+    }
+    /// Heals `amount` every tick
+    pub fn regen(amount: f32, duration: u64) -> Self {
+        Self::new("regen", duration, Stacking::Independent)
+            .with_modifier(EffectModifier::Heal(amount))
+    }
+    pub fn with_modifier(mut self, modifier: EffectModifier) -> Self {
+        self.modifiers.push(modifier);
+        self
+    }
+    /// Panics if `tick` is 0, which would fire damage and healing without end
+    pub fn with_tick(mut self, tick: u64) -> Self {
+        assert!(tick > 0, "a status effect's tick can't be 0");
+        self.tick = Duration::from_millis(tick);
+        self
+    }
+    pub fn with_tint(mut self, tint: Color) -> Self {
+        self.tint = Some(tint);
+        self
+    }
+}
+
+/// A [`StatusEffect`] that's currently applied to an entity
+#[derive(Debug)]
+pub struct ActiveStatusEffect {
+    pub effect: StatusEffect,
+    /// The entity that applied the effect, `None` for the environment
+    pub source: Option<Entity>,
+    pub stacks: u32,
+    pub remaining: Timer,
+    pub tick: Timer,
+}
+
+impl ActiveStatusEffect {
+    pub fn new(effect: StatusEffect, source: Option<Entity>) -> Self {
+        Self {
+            remaining: Timer::new(effect.duration, TimerMode::Once),
+            tick: Timer::new(effect.tick, TimerMode::Repeating),
+            effect,
+            source,
+            stacks: 1,
+        }
+    }
+    /// How much the effect's multipliers change a stat, with every stack applied
+    pub fn multiplier(&self, multiplier: f32) -> f32 {
+        multiplier.powi(self.stacks as i32)
+    }
+}
+
+/// The value of a stat before status effects changed it, along with the value they set
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StatBase<T> {
+    pub base: T,
+    pub applied: T,
+}
+
+impl<T: Copy + PartialEq> StatBase<T> {
+    /// The value the stat should have now, `modify` gives the modified value from the base or
+    /// `None` when no effect changes it, which forgets the base.
+    /// Changes made by anything else, like a movement profile reload, become the new base
+    pub fn resolve(base: &mut Option<Self>, current: T, modify: impl FnOnce(T) -> Option<T>) -> T {
+        let base_value = match *base {
+            Some(stat_base) if stat_base.applied == current => stat_base.base,
+            _ => current,
+        };
+        match modify(base_value) {
+            Some(applied) => {
+                *base = Some(StatBase {
+                    base: base_value,
+                    applied,
+                });
+                applied
+            }
+            None => {
+                *base = None;
+                base_value
+            }
+        }
+    }
+}
+
+/// The status effects currently applied to an entity
+#[derive(Component, Default, Debug)]
+pub struct StatusEffects {
+    pub active: Vec<ActiveStatusEffect>,
+    pub initial_run_speed: Option<StatBase<f32>>,
+    pub max_run_speed: Option<StatBase<f32>>,
+    pub sprite_color: Option<StatBase<Color>>,
+}
+
+impl StatusEffects {
+    /// Adds an effect following its [`Stacking`] rule
+    pub fn apply(&mut self, effect: StatusEffect, source: Option<Entity>) {
+        let existing = match effect.stacking {
+            Stacking::Independent => None,
+            Stacking::Refresh | Stacking::Intensity { .. } => self
+                .active
+                .iter()
+                .position(|active| active.effect.name == effect.name),
+        };
+        let Some(existing) = existing else {
+            self.active.push(ActiveStatusEffect::new(effect, source));
+            return;
+        };
+        let existing = &mut self.active[existing];
+        if let Stacking::Intensity { max_stacks } = effect.stacking {
+            existing.stacks = (existing.stacks + 1).min(max_stacks.max(1));
+        }
+        existing.remaining = Timer::new(effect.duration, TimerMode::Once);
+        existing.effect = effect;
+        existing.source = source;
+    }
+    /// The product of every active multiplier `select` picks out, `None` if there aren't any
+    pub fn multiplier(&self, select: impl Fn(&EffectModifier) -> Option<f32>) -> Option<f32> {
+        self.active
+            .iter()
+            .flat_map(|active| {
+                active
+                    .effect
+                    .modifiers
+                    .iter()
+                    .filter_map(&select)
+                    .map(|multiplier| active.multiplier(multiplier))
+            })
+            .reduce(|a, b| a * b)
+    }
+    /// The tint of the most recently applied effect that has one
+    pub fn tint(&self) -> Option<Color> {
+        self.active
+            .iter()
+            .rev()
+            .find_map(|active| active.effect.tint)
+    }
+}
+
+/// Applies its effects to every entity with [`StatusEffects`] inside it, again every frame they
+/// stay inside, so its effects should use [`Stacking::Refresh`]
+#[derive(Component, Clone, Debug)]
+pub struct EffectVolume(pub Vec<StatusEffect>);
+
+/// Asks for a [`StatusEffect`] to be applied to `target`
+#[derive(Event, Clone, Debug)]
+pub struct ApplyStatusEffect {
+    pub target: Entity,
+    pub source: Option<Entity>,
+    pub effect: StatusEffect,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_speed(name: &str, multiplier: f32, stacking: Stacking) -> StatusEffect {
+        StatusEffect::new(name, 1000, stacking).with_modifier(EffectModifier::RunSpeed(multiplier))
+    }
+
+    fn run_speed_multiplier(status_effects: &StatusEffects) -> Option<f32> {
+        status_effects.multiplier(|modifier| match modifier {
+            EffectModifier::RunSpeed(multiplier) => Some(*multiplier),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn resolve_remembers_the_base_while_modified() {
+        let mut base = None;
+        assert_eq!(
+            StatBase::resolve(&mut base, 10., |base| Some(base * 0.5)),
+            5.
+        );
+        assert_eq!(
+            base,
+            Some(StatBase {
+                base: 10.,
+                applied: 5.
+            })
+        );
+        // The applied value doesn't compound
+        assert_eq!(
+            StatBase::resolve(&mut base, 5., |base| Some(base * 0.5)),
+            5.
+        );
+    }
+
+    #[test]
+    fn resolve_restores_the_base_when_unmodified() {
+        let mut base = None;
+        StatBase::resolve(&mut base, 10., |base| Some(base * 0.5));
+        assert_eq!(StatBase::resolve(&mut base, 5., |_| None), 10.);
+        assert_eq!(base, None);
+    }
+
+    #[test]
+    fn resolve_takes_outside_changes_as_the_new_base() {
+        let mut base = None;
+        StatBase::resolve(&mut base, 10., |base| Some(base * 0.5));
+        assert_eq!(
+            StatBase::resolve(&mut base, 20., |base| Some(base * 0.5)),
+            10.
+        );
+        assert_eq!(StatBase::resolve(&mut base, 10., |_| None), 20.);
+    }
+
+    #[test]
+    fn refresh_restarts_the_existing_effect() {
+        let mut status_effects = StatusEffects::default();
+        status_effects.apply(run_speed("slow", 0.5, Stacking::Refresh), None);
+        status_effects.active[0]
+            .remaining
+            .tick(Duration::from_millis(600));
+        status_effects.apply(run_speed("slow", 0.5, Stacking::Refresh), None);
+        assert_eq!(status_effects.active.len(), 1);
+        assert_eq!(status_effects.active[0].stacks, 1);
+        assert!(status_effects.active[0].remaining.elapsed().is_zero());
+    }
+
+    #[test]
+    fn intensity_stacks_up_to_the_cap() {
+        let mut status_effects = StatusEffects::default();
+        for _ in 0..7 {
+            status_effects.apply(
+                run_speed("slow", 0.5, Stacking::Intensity { max_stacks: 5 }),
+                None,
+            );
+        }
+        assert_eq!(status_effects.active.len(), 1);
+        assert_eq!(status_effects.active[0].stacks, 5);
+    }
+
+    #[test]
+    fn independent_effects_run_alongside_each_other() {
+        let mut status_effects = StatusEffects::default();
+        status_effects.apply(run_speed("slow", 0.5, Stacking::Independent), None);
+        status_effects.apply(run_speed("slow", 0.5, Stacking::Independent), None);
+        assert_eq!(status_effects.active.len(), 2);
+        assert!(status_effects
+            .active
+            .iter()
+            .all(|active| active.stacks == 1));
+    }
+
+    #[test]
+    fn multiplier_is_the_product_of_every_stack_and_effect() {
+        let mut status_effects = StatusEffects::default();
+        assert_eq!(run_speed_multiplier(&status_effects), None);
+        status_effects.apply(
+            run_speed("slow", 0.5, Stacking::Intensity { max_stacks: 5 }),
+            None,
+        );
+        status_effects.apply(
+            run_speed("slow", 0.5, Stacking::Intensity { max_stacks: 5 }),
+            None,
+        );
+        status_effects.apply(run_speed("haste", 2., Stacking::Refresh), None);
+        status_effects.apply(StatusEffect::regen(1., 1000), None);
+        assert_eq!(run_speed_multiplier(&status_effects), Some(0.5));
+    }
+}
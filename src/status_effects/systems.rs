@@ -0,0 +1,189 @@
+use super::prelude::*;
+use avian2d::prelude::CollidingEntities;
+use bevy::{prelude::*, utils::HashSet};
+
+use crate::{DamageEvent, Died, Health, InitialRunSpeed, JumpHeightMultiplier, MaxRunSpeed};
+
+pub struct StatusEffectPlugin;
+
+impl Plugin for StatusEffectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ApplyStatusEffect>().add_systems(
+            Update,
+            (
+                apply_volume_effects,
+                apply_status_effects,
+                clear_status_effects_on_death,
+                tick_status_effects,
+                modify_movement_stats,
+                tint_sprites,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Applies the effects of every [`EffectVolume`] an entity is inside, once per effect name.
+fn apply_volume_effects(
+    volumes: Query<&EffectVolume>,
+    query: Query<(Entity, &CollidingEntities), With<StatusEffects>>,
+    mut apply_status_effect_event_writer: EventWriter<ApplyStatusEffect>,
+) {
+    for (entity, colliding_entities) in query.iter() {
+        let mut applied: HashSet<&str> = HashSet::new();
+        for effect in colliding_entities
+            .iter()
+            .filter_map(|colliding_entity| volumes.get(*colliding_entity).ok())
+            .flat_map(|volume| volume.0.iter())
+        {
+            if applied.insert(&effect.name) {
+                apply_status_effect_event_writer.send(ApplyStatusEffect {
+                    target: entity,
+                    source: None,
+                    effect: effect.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// Adds the effects from [`ApplyStatusEffect`] events to their target's [`StatusEffects`].
+fn apply_status_effects(
+    mut query: Query<&mut StatusEffects>,
+    mut apply_status_effect_event_reader: EventReader<ApplyStatusEffect>,
+) {
+    for apply_status_effect in apply_status_effect_event_reader.read() {
+        if let Ok(mut status_effects) = query.get_mut(apply_status_effect.target) {
+            status_effects.apply(
+                apply_status_effect.effect.clone(),
+                apply_status_effect.source,
+            );
+        }
+    }
+}
+
+/// Dead entities lose all their effects.
+fn clear_status_effects_on_death(
+    mut query: Query<&mut StatusEffects>,
+    mut died_event_reader: EventReader<Died>,
+) {
+    for died in died_event_reader.read() {
+        if let Ok(mut status_effects) = query.get_mut(died.entity) {
+            status_effects.active.clear();
+        }
+    }
+}
+
+/// Deals and heals over time for every tick of an effect, and removes effects once they run out.
+fn tick_status_effects(
+    mut query: Query<(Entity, &mut StatusEffects, Option<&mut Health>)>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+    time: Res<Time>,
+) {
+    for (entity, mut status_effects, mut health) in query.iter_mut() {
+        if status_effects.active.is_empty() {
+            continue;
+        }
+        for active in status_effects.active.iter_mut() {
+            active.remaining.tick(time.delta());
+            active.tick.tick(time.delta());
+            for _ in 0..active.tick.times_finished_this_tick() {
+                for modifier in active.effect.modifiers.iter() {
+                    match modifier {
+                        EffectModifier::Damage { amount, kind } => {
+                            damage_event_writer.send(DamageEvent {
+                                source: active.source,
+                                target: entity,
+                                amount: amount * active.stacks as f32,
+                                kind: *kind,
+                                periodic: true,
+                            });
+                        }
+                        EffectModifier::Heal(amount) => {
+                            if let Some(health) = &mut health {
+                                health.current = (health.current + amount * active.stacks as f32)
+                                    .min(health.max);
+                            }
+                        }
+                        EffectModifier::RunSpeed(_) | EffectModifier::JumpHeight(_) => {}
+                    }
+                }
+            }
+        }
+        status_effects
+            .active
+            .retain(|active| !active.remaining.finished());
+    }
+}
+
+type ModifiedCharacter<'a> = (
+    &'a mut StatusEffects,
+    Option<&'a mut InitialRunSpeed>,
+    Option<&'a mut MaxRunSpeed>,
+    Option<&'a mut JumpHeightMultiplier>,
+);
+
+/// Sets [`InitialRunSpeed`] and [`MaxRunSpeed`] from their base values and the active effects,
+/// putting them back once no effect changes them, and sets [`JumpHeightMultiplier`] to the product
+/// of the jump effects
+fn modify_movement_stats(mut query: Query<ModifiedCharacter>) {
+    for (mut status_effects, initial_run_speed, max_run_speed, jump_height_multiplier) in
+        query.iter_mut()
+    {
+        let run_speed_multiplier = status_effects.multiplier(|modifier| match modifier {
+            EffectModifier::RunSpeed(multiplier) => Some(*multiplier),
+            _ => None,
+        });
+        // Work on copies so the effects are only marked changed when a base is
+        if let Some(mut initial_run_speed) = initial_run_speed {
+            let mut base = status_effects.initial_run_speed;
+            let speed = StatBase::resolve(&mut base, initial_run_speed.0, |base| {
+                run_speed_multiplier.map(|multiplier| base * multiplier)
+            });
+            if status_effects.initial_run_speed != base {
+                status_effects.initial_run_speed = base;
+            }
+            if initial_run_speed.0 != speed {
+                initial_run_speed.0 = speed;
+            }
+        }
+        if let Some(mut max_run_speed) = max_run_speed {
+            let mut base = status_effects.max_run_speed;
+            let speed = StatBase::resolve(&mut base, max_run_speed.0, |base| {
+                run_speed_multiplier.map(|multiplier| base * multiplier)
+            });
+            if status_effects.max_run_speed != base {
+                status_effects.max_run_speed = base;
+            }
+            if max_run_speed.0 != speed {
+                max_run_speed.0 = speed;
+            }
+        }
+        if let Some(mut jump_height_multiplier) = jump_height_multiplier {
+            let multiplier = status_effects
+                .multiplier(|modifier| match modifier {
+                    EffectModifier::JumpHeight(multiplier) => Some(*multiplier),
+                    _ => None,
+                })
+                .unwrap_or(1.);
+            if jump_height_multiplier.0 != multiplier {
+                jump_height_multiplier.0 = multiplier;
+            }
+        }
+    }
+}
+
+/// Tints sprites with their most recent effect's colour, putting the colour back once it's gone.
+fn tint_sprites(mut query: Query<(&mut StatusEffects, &mut Sprite)>) {
+    for (mut status_effects, mut sprite) in query.iter_mut() {
+        let tint = status_effects.tint();
+        let mut base = status_effects.sprite_color;
+        let color = StatBase::resolve(&mut base, sprite.color, |_| tint);
+        if status_effects.sprite_color != base {
+            status_effects.sprite_color = base;
+        }
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}